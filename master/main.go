@@ -1,55 +1,112 @@
 package main
 
 import (
+	"crypto/subtle"
 	"encoding/json"
 	"fmt"
 	"log"
 	"net"
 	"net/http"
+	"os"
+	"strings"
 	"sync"
 	"time"
 )
 
+const protocolVersion = 1
+
 type Node struct {
-	ID       string    `json:"id"`
-	Address  string    `json:"address"`
-	Port     int       `json:"port"`
-	Status   string    `json:"status"`
-	LastSeen time.Time `json:"last_seen"`
-	Load     int       `json:"load"`
-	Capacity int       `json:"capacity"`
+	ID          string            `json:"id"`
+	Address     string            `json:"address"`
+	Port        int               `json:"port"`
+	Role        string            `json:"role"`
+	Status      string            `json:"status"`
+	LastSeen    time.Time         `json:"last_seen"`
+	Load        int               `json:"load"`
+	LoadPercent int               `json:"load_percent"`
+	Capacity    int               `json:"capacity"`
+	Uptime      int               `json:"uptime"`
+	Connections int               `json:"active_connections"`
+	Generation  uint64            `json:"generation"`
+	Version     string            `json:"version,omitempty"`
+	Service     string            `json:"service,omitempty"`
+	Metadata    map[string]string `json:"metadata,omitempty"`
 }
 
 type ClusterManager struct {
-	nodes map[string]*Node
-	mutex sync.RWMutex
+	nodes    map[string]*Node
+	commands map[string][]string
+	mutex    sync.RWMutex
 }
 
 func NewClusterManager() *ClusterManager {
 	return &ClusterManager{
-		nodes: make(map[string]*Node),
+		nodes:    make(map[string]*Node),
+		commands: make(map[string][]string),
 	}
 }
 
-func (cm *ClusterManager) RegisterNode(id, address string, port int) error {
+func (cm *ClusterManager) RegisterNode(id, address string, port int, role string, capacity int, generation uint64) error {
 	cm.mutex.Lock()
 	defer cm.mutex.Unlock()
 
+	if previous, exists := cm.nodes[id]; exists && previous.Generation != generation {
+		delete(cm.commands, id)
+		log.Printf("🔄 Нода %s перезапущена (поколение %d → %d), очередь команд сброшена", id, previous.Generation, generation)
+	}
+
 	node := &Node{
-		ID:       id,
-		Address:  address,
-		Port:     port,
-		Status:   "active",
-		LastSeen: time.Now(),
-		Load:     0,
-		Capacity: 100,
+		ID:         id,
+		Address:    address,
+		Port:       port,
+		Role:       role,
+		Status:     "active",
+		LastSeen:   time.Now(),
+		Load:       0,
+		Capacity:   capacity,
+		Generation: generation,
 	}
 
 	cm.nodes[id] = node
-	log.Printf("✅ Нода %s зарегистрирована: %s:%d", id, address, port)
+	log.Printf("✅ Нода %s (%s) зарегистрирована: %s:%d", id, role, address, port)
+	return nil
+}
+
+func (cm *ClusterManager) RemoveNode(id string) bool {
+	cm.mutex.Lock()
+	defer cm.mutex.Unlock()
+
+	if _, exists := cm.nodes[id]; !exists {
+		return false
+	}
+
+	delete(cm.nodes, id)
+	log.Printf("👋 Нода %s снята с регистрации", id)
+	return true
+}
+
+func (cm *ClusterManager) QueueCommand(id, command string) error {
+	cm.mutex.Lock()
+	defer cm.mutex.Unlock()
+
+	if _, exists := cm.nodes[id]; !exists {
+		return fmt.Errorf("нода %s не найдена", id)
+	}
+
+	cm.commands[id] = append(cm.commands[id], command)
+	log.Printf("📨 Команда %s поставлена в очередь для ноды %s", command, id)
 	return nil
 }
 
+func (cm *ClusterManager) TakeCommands(id string) []string {
+	cm.mutex.Lock()
+	defer cm.mutex.Unlock()
+
+	commands := cm.commands[id]
+	delete(cm.commands, id)
+	return commands
+}
+
 func (cm *ClusterManager) GetActiveNodes() []*Node {
 	cm.mutex.RLock()
 	defer cm.mutex.RUnlock()
@@ -63,7 +120,7 @@ func (cm *ClusterManager) GetActiveNodes() []*Node {
 	return activeNodes
 }
 
-func (cm *ClusterManager) UpdateNodeLoad(id string, load int) error {
+func (cm *ClusterManager) UpdateNodeLoad(id string, load, loadPercent int) error {
 	cm.mutex.Lock()
 	defer cm.mutex.Unlock()
 
@@ -73,6 +130,7 @@ func (cm *ClusterManager) UpdateNodeLoad(id string, load int) error {
 	}
 
 	node.Load = load
+	node.LoadPercent = loadPercent
 	node.LastSeen = time.Now()
 	return nil
 }
@@ -124,6 +182,8 @@ func (hs *HTTPServer) Start() error {
 	mux.HandleFunc("/api/cluster/status", hs.clusterStatusHandler)
 	mux.HandleFunc("/api/cluster/nodes", hs.clusterNodesHandler)
 	mux.HandleFunc("/api/cluster/register", hs.registerNodeHandler)
+	mux.HandleFunc("/api/cluster/shutdown", hs.shutdownNodeHandler)
+	mux.HandleFunc("/api/cluster/command", hs.commandNodeHandler)
 	mux.HandleFunc("/api/balancer/status", hs.balancerStatusHandler)
 
 	mux.HandleFunc("/", hs.proxyHandler)
@@ -169,9 +229,12 @@ func (hs *HTTPServer) registerNodeHandler(w http.ResponseWriter, r *http.Request
 	}
 
 	var req struct {
-		ID      string `json:"id"`
-		Address string `json:"address"`
-		Port    int    `json:"port"`
+		ID         string `json:"id"`
+		Address    string `json:"address"`
+		Port       int    `json:"port"`
+		Role       string `json:"role"`
+		Capacity   int    `json:"capacity"`
+		Generation uint64 `json:"generation"`
 	}
 
 	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
@@ -179,7 +242,7 @@ func (hs *HTTPServer) registerNodeHandler(w http.ResponseWriter, r *http.Request
 		return
 	}
 
-	if err := hs.clusterManager.RegisterNode(req.ID, req.Address, req.Port); err != nil {
+	if err := hs.clusterManager.RegisterNode(req.ID, req.Address, req.Port, nodeRole(req.Role), nodeCapacity(req.Capacity), req.Generation); err != nil {
 		http.Error(w, err.Error(), http.StatusInternalServerError)
 		return
 	}
@@ -188,6 +251,62 @@ func (hs *HTTPServer) registerNodeHandler(w http.ResponseWriter, r *http.Request
 	json.NewEncoder(w).Encode(map[string]string{"status": "registered"})
 }
 
+func (hs *HTTPServer) shutdownNodeHandler(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+
+	var req struct {
+		ID string `json:"id"`
+	}
+
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		http.Error(w, "Invalid JSON", http.StatusBadRequest)
+		return
+	}
+
+	if err := hs.clusterManager.QueueCommand(req.ID, "shutdown"); err != nil {
+		http.Error(w, err.Error(), http.StatusNotFound)
+		return
+	}
+
+	w.WriteHeader(http.StatusAccepted)
+	json.NewEncoder(w).Encode(map[string]string{"status": "queued"})
+}
+
+func (hs *HTTPServer) commandNodeHandler(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
+		return
+	}
+
+	var req struct {
+		ID      string `json:"id"`
+		Command string `json:"command"`
+	}
+
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		http.Error(w, "Invalid JSON", http.StatusBadRequest)
+		return
+	}
+
+	switch req.Command {
+	case "shutdown", "drain", "undrain", "config":
+	default:
+		http.Error(w, "Unknown command", http.StatusBadRequest)
+		return
+	}
+
+	if err := hs.clusterManager.QueueCommand(req.ID, req.Command); err != nil {
+		http.Error(w, err.Error(), http.StatusNotFound)
+		return
+	}
+
+	w.WriteHeader(http.StatusAccepted)
+	json.NewEncoder(w).Encode(map[string]string{"status": "queued"})
+}
+
 func (hs *HTTPServer) balancerStatusHandler(w http.ResponseWriter, r *http.Request) {
 	if r.Method != http.MethodGet {
 		http.Error(w, "Method not allowed", http.StatusMethodNotAllowed)
@@ -231,12 +350,14 @@ func (hs *HTTPServer) proxyHandler(w http.ResponseWriter, r *http.Request) {
 type SocketServer struct {
 	clusterManager *ClusterManager
 	port           int
+	authToken      string
 }
 
-func NewSocketServer(cm *ClusterManager, port int) *SocketServer {
+func NewSocketServer(cm *ClusterManager, port int, authToken string) *SocketServer {
 	return &SocketServer{
 		clusterManager: cm,
 		port:           port,
+		authToken:      authToken,
 	}
 }
 
@@ -295,11 +416,29 @@ func (ss *SocketServer) handleMessage(msg map[string]interface{}, conn net.Conn)
 		ss.handleHeartbeat(msg, conn)
 	case "load_update":
 		ss.handleLoadUpdate(msg, conn)
+	case "deregister":
+		ss.handleDeregister(msg, conn)
+	case "status_update":
+		ss.handleStatusUpdate(msg, conn)
+	case "ping":
+		ss.handlePing(conn)
+	case "info":
+		ss.handleInfo(msg, conn)
 	default:
 		log.Printf("❌ Неизвестный тип сообщения: %s", msgType)
 	}
 }
 
+func negotiateVersion(requested int) int {
+	if requested < 1 {
+		return 1
+	}
+	if requested > protocolVersion {
+		return protocolVersion
+	}
+	return requested
+}
+
 func (ss *SocketServer) handleRegister(msg map[string]interface{}, conn net.Conn) {
 	id, _ := msg["id"].(string)
 	address, _ := msg["address"].(string)
@@ -310,19 +449,38 @@ func (ss *SocketServer) handleRegister(msg map[string]interface{}, conn net.Conn
 		return
 	}
 
-	remoteAddr := conn.RemoteAddr().String()
-	host, _, err := net.SplitHostPort(remoteAddr)
-	if err == nil && host != "" {
-		address = host
+	if ss.authToken != "" {
+		token, _ := msg["token"].(string)
+		if subtle.ConstantTimeCompare([]byte(token), []byte(ss.authToken)) != 1 {
+			log.Printf("❌ Нода %s не прошла аутентификацию", id)
+			responseBytes, _ := json.Marshal(map[string]string{"status": "unauthorized"})
+			conn.Write(responseBytes)
+			return
+		}
 	}
 
-	err = ss.clusterManager.RegisterNode(id, address, int(port))
+	if ip := net.ParseIP(address); ip != nil && ip.IsUnspecified() {
+		remoteAddr := conn.RemoteAddr().String()
+		host, _, err := net.SplitHostPort(remoteAddr)
+		if err == nil && host != "" {
+			address = host
+		}
+	}
+
+	role, _ := msg["role"].(string)
+	capacity, _ := msg["capacity"].(float64)
+	generation, _ := msg["generation"].(float64)
+	err := ss.clusterManager.RegisterNode(id, address, int(port), nodeRole(role), nodeCapacity(int(capacity)), uint64(generation))
 	if err != nil {
 		log.Printf("❌ Ошибка регистрации ноды: %v", err)
 		return
 	}
 
-	response := map[string]string{"status": "registered"}
+	requested, _ := msg["version"].(float64)
+	response := map[string]interface{}{
+		"status":             "registered",
+		"negotiated_version": negotiateVersion(int(requested)),
+	}
 	responseBytes, _ := json.Marshal(response)
 	conn.Write(responseBytes)
 
@@ -335,26 +493,45 @@ func (ss *SocketServer) handleHeartbeat(msg map[string]interface{}, conn net.Con
 		return
 	}
 
+	uptime, _ := msg["uptime"].(float64)
+	connections, _ := msg["active_connections"].(float64)
+
 	ss.clusterManager.mutex.Lock()
 	if node, exists := ss.clusterManager.nodes[id]; exists {
 		node.LastSeen = time.Now()
+		node.Uptime = int(uptime)
+		node.Connections = int(connections)
 	}
 	ss.clusterManager.mutex.Unlock()
 
 	response := map[string]string{"status": "ok"}
 	responseBytes, _ := json.Marshal(response)
 	conn.Write(responseBytes)
+
+	for _, command := range ss.clusterManager.TakeCommands(id) {
+		frame := map[string]string{"command": command}
+		if ss.authToken != "" {
+			frame["token"] = ss.authToken
+		}
+		frameBytes, _ := json.Marshal(frame)
+		conn.Write(append([]byte("\n"), frameBytes...))
+		log.Printf("📨 Нода %s получила команду %s", id, command)
+	}
 }
 
 func (ss *SocketServer) handleLoadUpdate(msg map[string]interface{}, conn net.Conn) {
 	id, _ := msg["id"].(string)
 	load, _ := msg["load"].(float64)
+	loadPercent, ok := msg["load_percent"].(float64)
+	if !ok {
+		loadPercent = load
+	}
 
 	if id == "" {
 		return
 	}
 
-	err := ss.clusterManager.UpdateNodeLoad(id, int(load))
+	err := ss.clusterManager.UpdateNodeLoad(id, int(load), int(loadPercent))
 	if err != nil {
 		log.Printf("❌ Ошибка обновления нагрузки: %v", err)
 		return
@@ -365,9 +542,147 @@ func (ss *SocketServer) handleLoadUpdate(msg map[string]interface{}, conn net.Co
 	conn.Write(responseBytes)
 }
 
+func (ss *SocketServer) handleInfo(msg map[string]interface{}, conn net.Conn) {
+	id, _ := msg["id"].(string)
+	if id == "" {
+		return
+	}
+
+	role, _ := msg["role"].(string)
+	capacity, _ := msg["capacity"].(float64)
+	version, _ := msg["worker_version"].(string)
+	service, _ := msg["service_name"].(string)
+	metadata := make(map[string]string)
+	if values, ok := msg["metadata"].(map[string]interface{}); ok {
+		for key, value := range values {
+			if text, ok := value.(string); ok {
+				metadata[key] = text
+			}
+		}
+	}
+
+	ss.clusterManager.mutex.Lock()
+	node, exists := ss.clusterManager.nodes[id]
+	if exists {
+		node.LastSeen = time.Now()
+		node.Role = nodeRole(role)
+		node.Capacity = nodeCapacity(int(capacity))
+		node.Version = version
+		node.Service = service
+		node.Metadata = metadata
+	}
+	ss.clusterManager.mutex.Unlock()
+
+	if !exists {
+		log.Printf("❌ Сведения от незарегистрированной ноды %s", id)
+		return
+	}
+
+	responseBytes, _ := json.Marshal(map[string]string{"status": "ok"})
+	conn.Write(responseBytes)
+}
+
+func (ss *SocketServer) handlePing(conn net.Conn) {
+	responseBytes, _ := json.Marshal(map[string]string{"status": "pong"})
+	conn.Write(responseBytes)
+}
+
+func (ss *SocketServer) handleDeregister(msg map[string]interface{}, conn net.Conn) {
+	id, _ := msg["id"].(string)
+	if id == "" {
+		return
+	}
+
+	if ss.authToken != "" {
+		token, _ := msg["token"].(string)
+		if subtle.ConstantTimeCompare([]byte(token), []byte(ss.authToken)) != 1 {
+			log.Printf("❌ Нода %s не прошла аутентификацию при снятии с регистрации", id)
+			responseBytes, _ := json.Marshal(map[string]string{"status": "unauthorized"})
+			conn.Write(responseBytes)
+			return
+		}
+	}
+
+	ss.clusterManager.RemoveNode(id)
+
+	response := map[string]string{"status": "deregistered"}
+	responseBytes, _ := json.Marshal(response)
+	conn.Write(responseBytes)
+}
+
+func (ss *SocketServer) handleStatusUpdate(msg map[string]interface{}, conn net.Conn) {
+	id, _ := msg["id"].(string)
+	status, _ := msg["status"].(string)
+	if id == "" {
+		return
+	}
+
+	if ss.authToken != "" {
+		token, _ := msg["token"].(string)
+		if subtle.ConstantTimeCompare([]byte(token), []byte(ss.authToken)) != 1 {
+			log.Printf("❌ Нода %s не прошла аутентификацию при обновлении статуса", id)
+			responseBytes, _ := json.Marshal(map[string]string{"status": "unauthorized"})
+			conn.Write(responseBytes)
+			return
+		}
+	}
+
+	nodeStatus := map[string]string{"draining": "draining", "ready": "active"}[status]
+	if nodeStatus == "" {
+		log.Printf("❌ Неизвестный статус ноды %s: %s", id, status)
+		responseBytes, _ := json.Marshal(map[string]string{"status": "unknown_status"})
+		conn.Write(responseBytes)
+		return
+	}
+
+	ss.clusterManager.mutex.Lock()
+	node, exists := ss.clusterManager.nodes[id]
+	if exists {
+		node.Status = nodeStatus
+	}
+	ss.clusterManager.mutex.Unlock()
+
+	if !exists {
+		responseBytes, _ := json.Marshal(map[string]string{"status": "unknown_node"})
+		conn.Write(responseBytes)
+		return
+	}
+
+	log.Printf("🚰 Нода %s сменила статус: %s", id, nodeStatus)
+	responseBytes, _ := json.Marshal(map[string]string{"status": "updated"})
+	conn.Write(responseBytes)
+}
+
+func nodeRole(role string) string {
+	if role == "" {
+		return "worker"
+	}
+	return role
+}
+
+func nodeCapacity(capacity int) int {
+	if capacity <= 0 {
+		return 100
+	}
+	return capacity
+}
+
+func readSecret(name string) string {
+	if path := os.Getenv(name + "_FILE"); path != "" {
+		data, err := os.ReadFile(path)
+		if err != nil {
+			log.Fatalf("❌ Не удалось прочитать %s_FILE: %v", name, err)
+		}
+		return strings.TrimRight(string(data), "\r\n")
+	}
+	return os.Getenv(name)
+}
+
 func main() {
 	log.Println("🚀 Запуск центрального сервера...")
 
+	authToken := readSecret("AUTH_TOKEN")
+
 	clusterManager := NewClusterManager()
 
 	loadBalancer := NewLoadBalancer(clusterManager)
@@ -379,7 +694,7 @@ func main() {
 		}
 	}()
 
-	socketServer := NewSocketServer(clusterManager, 8081)
+	socketServer := NewSocketServer(clusterManager, 8081, authToken)
 	if err := socketServer.Start(); err != nil {
 		log.Fatalf("❌ Ошибка сокет сервера: %v", err)
 	}
@@ -1,12 +1,15 @@
 package main
 
 import (
+	"bufio"
 	"encoding/json"
 	"fmt"
 	"io"
 	"net/http"
 	"os"
+	"strconv"
 	"strings"
+	"sync"
 	"time"
 )
 
@@ -32,11 +35,14 @@ type ClusterStatus struct {
 }
 
 type NodeInfo struct {
-	ID      string `json:"id"`
-	Address string `json:"address"`
-	Port    int    `json:"port"`
-	Status  string `json:"status"`
-	Load    int    `json:"load"`
+	ID         string `json:"id"`
+	Address    string `json:"address"`
+	Port       int    `json:"port"`
+	Role       string `json:"role"`
+	Status     string `json:"status"`
+	Load       int    `json:"load"`
+	Generation uint64 `json:"generation"`
+	Version    string `json:"version"`
 }
 
 type HealthResponse struct {
@@ -46,6 +52,22 @@ type HealthResponse struct {
 	Uptime int64  `json:"uptime"`
 }
 
+type StatusResponse struct {
+	Status     string `json:"status"`
+	NodeID     string `json:"node_id"`
+	Load       int    `json:"load"`
+	Capacity   int    `json:"capacity"`
+	Headroom   int    `json:"headroom"`
+	Registered bool   `json:"registered"`
+}
+
+type StatsResponse struct {
+	WindowSecs        int     `json:"window_secs"`
+	HeartbeatsPerSec  float64 `json:"heartbeats_per_sec"`
+	LoadUpdatesPerSec float64 `json:"load_updates_per_sec"`
+	RequestsPerSec    float64 `json:"requests_per_sec"`
+}
+
 type ProxyResponse struct {
 	Message   string                 `json:"message"`
 	Node      map[string]interface{} `json:"node"`
@@ -76,8 +98,17 @@ func main() {
 	testMasterAPI(&results, masterURL)
 	testWorkerRegistration(&results, masterURL)
 	testWorkerHealth(&results, worker1URL, worker2URL)
+	testWorkerStatus(&results, worker1URL, worker2URL)
+	testRequestID(&results, worker1URL)
+	testWhoami(&results, worker1URL)
+	testProtocolNegotiation(&results, worker1URL)
+	testWorkerStats(&results, worker1URL)
+	testLiveLoad(&results, worker1URL)
 	testSocketCommunication(&results, masterURL)
 	testLoadBalancing(&results, masterURL)
+	testRateLimit(&results, worker2URL)
+	testCommandFrames(&results, masterURL, worker1URL)
+	testConcurrentDrain(&results, masterURL, worker1URL)
 
 	generateReport(results)
 }
@@ -138,7 +169,20 @@ func testWorkerRegistration(results *[]TestResult, masterURL string) {
 		body, _ := io.ReadAll(resp.Body)
 		json.Unmarshal(body, &nodes)
 
-		if len(nodes) >= 2 {
+		withRole, withGeneration, withVersion := 0, 0, 0
+		for _, node := range nodes {
+			if node.Role != "" {
+				withRole++
+			}
+			if node.Generation != 0 {
+				withGeneration++
+			}
+			if node.Version != "" {
+				withVersion++
+			}
+		}
+
+		if len(nodes) >= 2 && withRole == len(nodes) && withGeneration == len(nodes) && withVersion == len(nodes) {
 			fmt.Printf("✅ Зарегистрировано %d нод\n", len(nodes))
 			*results = append(*results, TestResult{Test: "Worker Registration", Status: "PASS"})
 		} else {
@@ -177,6 +221,238 @@ func testWorkerHealth(results *[]TestResult, worker1URL, worker2URL string) {
 	}
 }
 
+func testWorkerStatus(results *[]TestResult, worker1URL, worker2URL string) {
+	fmt.Println("🧪 Тестирование статуса рабочих нод...")
+
+	workers := []struct {
+		name string
+		url  string
+	}{
+		{"Worker-1", worker1URL},
+		{"Worker-2", worker2URL},
+	}
+
+	for _, worker := range workers {
+		resp, err := http.Get(worker.url + "/api/status")
+		if err != nil || resp.StatusCode != 200 {
+			fmt.Printf("❌ %s не отдаёт статус\n", worker.name)
+			*results = append(*results, TestResult{Test: worker.name + " Status", Status: "FAIL"})
+			continue
+		}
+
+		var status StatusResponse
+		body, _ := io.ReadAll(resp.Body)
+		json.Unmarshal(body, &status)
+
+		expectedHeadroom := status.Capacity - status.Load
+		if expectedHeadroom < 0 {
+			expectedHeadroom = 0
+		}
+
+		withinCapacity := status.Load >= 0 && status.Load <= status.Capacity
+		if status.Registered && status.Capacity > 0 && withinCapacity && status.Headroom == expectedHeadroom {
+			fmt.Printf("✅ %s: ёмкость %d, запас %d\n", worker.name, status.Capacity, status.Headroom)
+			*results = append(*results, TestResult{Test: worker.name + " Status", Status: "PASS"})
+		} else {
+			fmt.Printf("❌ %s: некорректный статус %+v\n", worker.name, status)
+			*results = append(*results, TestResult{Test: worker.name + " Status", Status: "FAIL"})
+		}
+	}
+}
+
+func testWorkerStats(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование /api/stats...")
+
+	resp, err := http.Get(workerURL + "/api/stats")
+	if err != nil || resp.StatusCode != 200 {
+		fmt.Println("❌ Worker-1 не отдаёт статистику")
+		*results = append(*results, TestResult{Test: "Worker Stats", Status: "FAIL"})
+		return
+	}
+
+	var stats StatsResponse
+	body, _ := io.ReadAll(resp.Body)
+	json.Unmarshal(body, &stats)
+
+	if stats.WindowSecs > 0 && stats.WindowSecs <= 60 && stats.RequestsPerSec > 0 {
+		fmt.Printf("✅ Статистика за %d с: %.2f запросов/с, %.2f heartbeat/с\n",
+			stats.WindowSecs, stats.RequestsPerSec, stats.HeartbeatsPerSec)
+		*results = append(*results, TestResult{Test: "Worker Stats", Status: "PASS"})
+	} else {
+		fmt.Printf("❌ Некорректная статистика: %+v\n", stats)
+		*results = append(*results, TestResult{Test: "Worker Stats", Status: "FAIL"})
+	}
+}
+
+func testLiveLoad(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование потока /api/live...")
+
+	client := &http.Client{Timeout: 15 * time.Second}
+	resp, err := client.Get(workerURL + "/api/live")
+	if err != nil || resp.StatusCode != 200 {
+		fmt.Println("❌ Worker-1 не отдаёт поток нагрузки")
+		*results = append(*results, TestResult{Test: "Worker Live Load", Status: "FAIL"})
+		return
+	}
+	defer resp.Body.Close()
+
+	scanner := bufio.NewScanner(resp.Body)
+	for scanner.Scan() {
+		line := scanner.Text()
+		if !strings.HasPrefix(line, "data: ") {
+			continue
+		}
+
+		var sample struct {
+			Load        int `json:"load"`
+			LoadPercent int `json:"load_percent"`
+		}
+		if json.Unmarshal([]byte(strings.TrimPrefix(line, "data: ")), &sample) == nil && sample.LoadPercent >= 0 {
+			fmt.Printf("✅ Получено обновление нагрузки: %d (%d%%)\n", sample.Load, sample.LoadPercent)
+			*results = append(*results, TestResult{Test: "Worker Live Load", Status: "PASS"})
+			return
+		}
+	}
+
+	fmt.Println("❌ Обновление нагрузки не получено")
+	*results = append(*results, TestResult{Test: "Worker Live Load", Status: "FAIL"})
+}
+
+func testRateLimit(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование ограничения частоты запросов...")
+
+	for i := 0; i < 100; i++ {
+		resp, err := http.Get(workerURL + "/api/whoami")
+		if err != nil {
+			continue
+		}
+		body, _ := io.ReadAll(resp.Body)
+		resp.Body.Close()
+
+		if resp.StatusCode != http.StatusTooManyRequests {
+			continue
+		}
+
+		retryAfter, err := strconv.Atoi(resp.Header.Get("Retry-After"))
+		if err != nil || retryAfter < 1 || !strings.Contains(string(body), "rate limited") {
+			fmt.Printf("❌ Некорректный ответ лимита: Retry-After=%q, тело=%s\n", resp.Header.Get("Retry-After"), body)
+			*results = append(*results, TestResult{Test: "Rate Limit", Status: "FAIL"})
+			return
+		}
+		fmt.Printf("✅ Лимит сработал на запросе %d, Retry-After: %d\n", i+1, retryAfter)
+
+		for j := 0; j < 20; j++ {
+			healthResp, err := http.Get(workerURL + "/api/health")
+			if err != nil || healthResp.StatusCode != http.StatusOK {
+				fmt.Println("❌ /api/health попал под лимит частоты")
+				*results = append(*results, TestResult{Test: "Rate Limit", Status: "FAIL"})
+				return
+			}
+			healthResp.Body.Close()
+		}
+		fmt.Println("✅ /api/health отвечает 200 при исчерпанном лимите")
+		*results = append(*results, TestResult{Test: "Rate Limit", Status: "PASS"})
+		return
+	}
+
+	fmt.Println("❌ Лимит не сработал за 100 запросов")
+	*results = append(*results, TestResult{Test: "Rate Limit", Status: "FAIL"})
+}
+
+func testWhoami(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование /api/whoami...")
+
+	resp, err := http.Get(workerURL + "/api/whoami")
+	if err != nil || resp.StatusCode != 200 {
+		fmt.Println("❌ Worker-1 не отдаёт /api/whoami")
+		*results = append(*results, TestResult{Test: "Worker Whoami", Status: "FAIL"})
+		return
+	}
+	body, _ := io.ReadAll(resp.Body)
+	resp.Body.Close()
+
+	var health HealthResponse
+	healthResp, err := http.Get(workerURL + "/api/health")
+	if err == nil {
+		healthBody, _ := io.ReadAll(healthResp.Body)
+		healthResp.Body.Close()
+		json.Unmarshal(healthBody, &health)
+	}
+
+	contentType := resp.Header.Get("Content-Type")
+	if strings.HasPrefix(contentType, "text/plain") && string(body) == health.NodeID && health.NodeID != "" {
+		fmt.Printf("✅ /api/whoami: %s\n", body)
+		*results = append(*results, TestResult{Test: "Worker Whoami", Status: "PASS"})
+	} else {
+		fmt.Printf("❌ /api/whoami вернул %q (%s), ожидался %q\n", body, contentType, health.NodeID)
+		*results = append(*results, TestResult{Test: "Worker Whoami", Status: "FAIL"})
+	}
+}
+
+func testProtocolNegotiation(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование согласования версии протокола...")
+
+	resp, err := http.Get(workerURL + "/api/info")
+	if err != nil {
+		fmt.Println("❌ Worker-1 недоступен")
+		*results = append(*results, TestResult{Test: "Protocol Negotiation", Status: "FAIL"})
+		return
+	}
+	defer resp.Body.Close()
+
+	var info struct {
+		ProtocolVersion int `json:"protocol_version"`
+	}
+	json.NewDecoder(resp.Body).Decode(&info)
+
+	if info.ProtocolVersion == 1 {
+		fmt.Println("✅ Нода с протоколом v2 перешла на v1 мастера")
+		*results = append(*results, TestResult{Test: "Protocol Negotiation", Status: "PASS"})
+	} else {
+		fmt.Printf("❌ Ожидалась версия 1, нода использует %d\n", info.ProtocolVersion)
+		*results = append(*results, TestResult{Test: "Protocol Negotiation", Status: "FAIL"})
+	}
+}
+
+func testRequestID(results *[]TestResult, workerURL string) {
+	fmt.Println("🧪 Тестирование X-Request-Id...")
+
+	cases := []struct {
+		name     string
+		incoming string
+		reflect  bool
+	}{
+		{"Valid Request ID", "abc-123", true},
+		{"Malformed Request ID", "bad id;<script>", false},
+	}
+
+	for _, test := range cases {
+		req, _ := http.NewRequest(http.MethodGet, workerURL+"/api/health", nil)
+		req.Header.Set("X-Request-Id", test.incoming)
+
+		resp, err := http.DefaultClient.Do(req)
+		if err != nil {
+			fmt.Printf("❌ %s - запрос не выполнен: %v\n", test.name, err)
+			*results = append(*results, TestResult{Test: test.name, Status: "FAIL"})
+			continue
+		}
+
+		reflected := resp.Header.Get("X-Request-Id")
+		ok := reflected == test.incoming
+		if !test.reflect {
+			ok = reflected != "" && !strings.ContainsAny(reflected, " ;<>")
+		}
+
+		if ok {
+			fmt.Printf("✅ %s - OK (%s)\n", test.name, reflected)
+			*results = append(*results, TestResult{Test: test.name, Status: "PASS"})
+		} else {
+			fmt.Printf("❌ %s - получено %q\n", test.name, reflected)
+			*results = append(*results, TestResult{Test: test.name, Status: "FAIL"})
+		}
+	}
+}
+
 func testSocketCommunication(results *[]TestResult, masterURL string) {
 	fmt.Println("🧪 Тестирование сокетной связи...")
 
@@ -270,3 +546,125 @@ func generateReport(results []TestResult) {
 		fmt.Println(" Некоторые тесты провалились!")
 	}
 }
+
+func queueCommand(masterURL, id, command string) bool {
+	body := fmt.Sprintf(`{"id":%q,"command":%q}`, id, command)
+	resp, err := http.Post(masterURL+"/api/cluster/command", "application/json", strings.NewReader(body))
+	if err != nil {
+		return false
+	}
+	resp.Body.Close()
+	return resp.StatusCode == http.StatusAccepted
+}
+
+func waitForWorkerStatus(workerURL, want string) bool {
+	for i := 0; i < 30; i++ {
+		resp, err := http.Get(workerURL + "/api/status")
+		if err == nil {
+			var status StatusResponse
+			decodeErr := json.NewDecoder(resp.Body).Decode(&status)
+			resp.Body.Close()
+			if decodeErr == nil && status.Status == want {
+				return true
+			}
+		}
+		time.Sleep(1 * time.Second)
+	}
+	return false
+}
+
+func testCommandFrames(results *[]TestResult, masterURL, workerURL string) {
+	fmt.Println("🧪 Тестирование кадров команд мастера...")
+
+	resp, err := http.Get(workerURL + "/api/health")
+	if err != nil {
+		fmt.Println("❌ Worker-1 недоступен")
+		*results = append(*results, TestResult{Test: "Master Command Frames", Status: "FAIL"})
+		return
+	}
+	var health HealthResponse
+	json.NewDecoder(resp.Body).Decode(&health)
+	resp.Body.Close()
+
+	if !queueCommand(masterURL, health.NodeID, "config") || !queueCommand(masterURL, health.NodeID, "drain") {
+		fmt.Println("❌ Мастер не принял команды")
+		*results = append(*results, TestResult{Test: "Master Command Frames", Status: "FAIL"})
+		return
+	}
+
+	if waitForWorkerStatus(workerURL, "draining") {
+		fmt.Println("✅ Ответ и два кадра команд обработаны, нода выведена из балансировки")
+		*results = append(*results, TestResult{Test: "Master Command Frames", Status: "PASS"})
+	} else {
+		fmt.Println("❌ Нода не выполнила команды мастера")
+		*results = append(*results, TestResult{Test: "Master Command Frames", Status: "FAIL"})
+	}
+
+	queueCommand(masterURL, health.NodeID, "undrain")
+	waitForWorkerStatus(workerURL, "active")
+}
+
+func statusUpdatesSent(workerURL string) (int, bool) {
+	resp, err := http.Get(workerURL + "/metrics")
+	if err != nil {
+		return 0, false
+	}
+	defer resp.Body.Close()
+
+	scanner := bufio.NewScanner(resp.Body)
+	for scanner.Scan() {
+		value, found := strings.CutPrefix(scanner.Text(), `worker_master_messages_total{type="status_update"} `)
+		if !found {
+			continue
+		}
+		count, err := strconv.Atoi(value)
+		return count, err == nil
+	}
+	return 0, false
+}
+
+func testConcurrentDrain(results *[]TestResult, masterURL, workerURL string) {
+	fmt.Println("🧪 Тестирование одновременных команд drain...")
+
+	resp, err := http.Get(workerURL + "/api/health")
+	if err != nil {
+		fmt.Println("❌ Worker-1 недоступен")
+		*results = append(*results, TestResult{Test: "Concurrent Drain", Status: "FAIL"})
+		return
+	}
+	var health HealthResponse
+	json.NewDecoder(resp.Body).Decode(&health)
+	resp.Body.Close()
+
+	time.Sleep(1 * time.Second)
+	before, ok := statusUpdatesSent(workerURL)
+	if !ok {
+		fmt.Println("❌ Счётчик status_update не найден в /metrics")
+		*results = append(*results, TestResult{Test: "Concurrent Drain", Status: "FAIL"})
+		return
+	}
+
+	var wg sync.WaitGroup
+	for i := 0; i < 5; i++ {
+		wg.Add(1)
+		go func() {
+			defer wg.Done()
+			queueCommand(masterURL, health.NodeID, "drain")
+		}()
+	}
+	wg.Wait()
+
+	drained := waitForWorkerStatus(workerURL, "draining")
+	time.Sleep(1 * time.Second)
+	after, _ := statusUpdatesSent(workerURL)
+	if drained && after-before == 1 {
+		fmt.Println("✅ Пять команд drain дали один переход и одно сообщение мастеру")
+		*results = append(*results, TestResult{Test: "Concurrent Drain", Status: "PASS"})
+	} else {
+		fmt.Printf("❌ Ожидалось одно сообщение status_update, отправлено %d\n", after-before)
+		*results = append(*results, TestResult{Test: "Concurrent Drain", Status: "FAIL"})
+	}
+
+	queueCommand(masterURL, health.NodeID, "undrain")
+	waitForWorkerStatus(workerURL, "active")
+}
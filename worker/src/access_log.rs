@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+use crate::request_id::RequestId;
+use crate::NodeState;
+
+#[derive(Clone, Serialize)]
+pub struct AccessRecord {
+    pub timestamp: u64,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub duration_ms: f64,
+    pub request_id: String,
+}
+
+pub struct AccessLog {
+    entries: Mutex<VecDeque<AccessRecord>>,
+    limit: usize,
+}
+
+impl AccessLog {
+    pub fn new(limit: usize) -> Self {
+        AccessLog {
+            entries: Mutex::new(VecDeque::new()),
+            limit,
+        }
+    }
+    
+    fn record(&self, record: AccessRecord) {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == self.limit {
+            entries.pop_front();
+        }
+        entries.push_back(record);
+    }
+    
+    pub fn snapshot(&self) -> Vec<AccessRecord> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+    
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+pub async fn record_access(State(state): State<NodeState>, request: Request, next: Next) -> Response {
+    if state.access_log.limit == 0 {
+        return next.run(request).await;
+    }
+    
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let path = request.uri().path().to_string();
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_default();
+    
+    let response = next.run(request).await;
+    state.access_log.record(AccessRecord {
+        timestamp: crate::unix_time(),
+        method,
+        path,
+        status: response.status().as_u16(),
+        duration_ms: started.elapsed().as_micros() as f64 / 1000.0,
+        request_id,
+    });
+    response
+}
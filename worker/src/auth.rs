@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use tracing::warn;
+
+use crate::NodeState;
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+pub async fn require_admin(State(state): State<NodeState>, request: Request, next: Next) -> Response {
+    let config = state.config();
+    let Some(expected) = config.admin_token.as_deref() else {
+        return reject(StatusCode::FORBIDDEN, "admin token is not configured");
+    };
+    
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => next.run(request).await,
+        _ => {
+            warn!("🔒 Отклонён запрос без admin-токена: {}", request.uri().path());
+            reject(StatusCode::UNAUTHORIZED, "invalid or missing admin token")
+        }
+    }
+}
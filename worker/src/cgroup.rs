@@ -0,0 +1,74 @@
+use std::fs;
+
+const V2_CPU_MAX: &str = "/sys/fs/cgroup/cpu.max";
+const V2_MEMORY_MAX: &str = "/sys/fs/cgroup/memory.max";
+const V1_CPU_QUOTA: &str = "/sys/fs/cgroup/cpu/cpu.cfs_quota_us";
+const V1_CPU_PERIOD: &str = "/sys/fs/cgroup/cpu/cpu.cfs_period_us";
+const V1_MEMORY_LIMIT: &str = "/sys/fs/cgroup/memory/memory.limit_in_bytes";
+
+const GIB: f64 = (1u64 << 30) as f64;
+const UNLIMITED_MEMORY: u64 = 1 << 60;
+
+pub struct Limits {
+    pub version: &'static str,
+    pub cpus: Option<f64>,
+    pub memory_bytes: Option<u64>,
+}
+
+fn read(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|contents| contents.trim().to_string())
+}
+
+fn memory(value: &str) -> Option<u64> {
+    value.parse().ok().filter(|&bytes| bytes > 0 && bytes < UNLIMITED_MEMORY)
+}
+
+fn cpus(quota: &str, period: &str) -> Option<f64> {
+    let quota: f64 = quota.parse().ok().filter(|&quota: &f64| quota > 0.0)?;
+    let period: f64 = period.parse().ok().filter(|&period: &f64| period > 0.0)?;
+    Some(quota / period)
+}
+
+pub fn limits() -> Option<Limits> {
+    let cpu_max = read(V2_CPU_MAX);
+    let memory_max = read(V2_MEMORY_MAX);
+    if cpu_max.is_some() || memory_max.is_some() {
+        return Some(Limits {
+            version: "v2",
+            cpus: cpu_max.and_then(|value| {
+                let (quota, period) = value.split_once(' ')?;
+                cpus(quota, period)
+            }),
+            memory_bytes: memory_max.as_deref().and_then(memory),
+        });
+    }
+    
+    let quota = read(V1_CPU_QUOTA);
+    let memory_limit = read(V1_MEMORY_LIMIT);
+    if quota.is_some() || memory_limit.is_some() {
+        return Some(Limits {
+            version: "v1",
+            cpus: quota.zip(read(V1_CPU_PERIOD)).and_then(|(quota, period)| cpus(&quota, &period)),
+            memory_bytes: memory_limit.as_deref().and_then(memory),
+        });
+    }
+    
+    None
+}
+
+pub fn capacity(per_cpu: i32, per_gib: i32, fallback: i32) -> (i32, String) {
+    let Some(limits) = limits() else {
+        return (fallback, format!("файлы cgroup не найдены, используется {}", fallback));
+    };
+    
+    let by_cpu = limits.cpus.map(|cpus| ((cpus * per_cpu as f64) as i32, format!("CPU: {:.2}, на ядро: {}", cpus, per_cpu)));
+    let by_memory = limits.memory_bytes.filter(|_| per_gib > 0).map(|bytes| {
+        let gib = bytes as f64 / GIB;
+        ((gib * per_gib as f64) as i32, format!("память: {:.2} ГиБ, на ГиБ: {}", gib, per_gib))
+    });
+    
+    match by_cpu.into_iter().chain(by_memory).min_by_key(|(capacity, _)| *capacity) {
+        Some((capacity, reason)) => (capacity.max(1), format!("cgroup {}, {}", limits.version, reason)),
+        None => (fallback, format!("cgroup {} без ограничений, используется {}", limits.version, fallback)),
+    }
+}
@@ -0,0 +1,100 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub struct Frames<R, C> {
+    pub reply: R,
+    pub commands: Vec<C>,
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T> {
+    #[serde(flatten)]
+    message: &'a T,
+    version: u32,
+}
+
+pub trait Codec {
+    fn encode<T: Serialize>(&self, message: &T, version: u32) -> Result<String, String>;
+    
+    fn decode<R: DeserializeOwned, C: DeserializeOwned>(&self, text: &str) -> Result<Option<Frames<R, C>>, String>;
+}
+
+pub struct Json;
+
+impl Codec for Json {
+    fn encode<T: Serialize>(&self, message: &T, version: u32) -> Result<String, String> {
+        let encoded = if version >= 2 {
+            serde_json::to_string(&Versioned { message, version })
+        } else {
+            serde_json::to_string(message)
+        };
+        encoded.map_err(|e| format!("сообщение не сериализовано в JSON: {}", e))
+    }
+    
+    fn decode<R: DeserializeOwned, C: DeserializeOwned>(&self, text: &str) -> Result<Option<Frames<R, C>>, String> {
+        let mut frames = serde_json::Deserializer::from_str(text).into_iter::<serde_json::Value>();
+        let reply = match frames.next() {
+            Some(frame) => frame
+                .and_then(serde_json::from_value)
+                .map_err(|e| format!("ответ мастера не является корректным JSON: {}", e))?,
+            None => return Ok(None),
+        };
+        
+        let commands = frames
+            .map(|frame| {
+                frame
+                    .and_then(serde_json::from_value)
+                    .map_err(|e| format!("кадр команды мастера некорректен: {}", e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Frames { reply, commands }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    
+    #[derive(Serialize)]
+    struct Message {
+        id: &'static str,
+        load_f: f32,
+    }
+    
+    #[test]
+    fn encodes_v1_without_version() {
+        let encoded = Json.encode(&Message { id: "a", load_f: 0.5 }, 1).unwrap();
+        assert_eq!(encoded, r#"{"id":"a","load_f":0.5}"#);
+    }
+    
+    #[test]
+    fn encodes_v2_as_v1_plus_version() {
+        let encoded = Json.encode(&Message { id: "a", load_f: 0.5 }, 2).unwrap();
+        assert_eq!(encoded, r#"{"id":"a","load_f":0.5,"version":2}"#);
+    }
+    
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Reply {
+        status: String,
+    }
+    
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Command {
+        command: String,
+    }
+    
+    #[test]
+    fn decodes_reply_followed_by_command_frames() {
+        let text = "{\"status\":\"ok\"}\n{\"command\":\"drain\"}\n{\"command\":\"reload_config\"}\n";
+        let frames = Json.decode::<Reply, Command>(text).unwrap().unwrap();
+        assert_eq!(frames.reply, Reply { status: "ok".to_string() });
+        assert_eq!(
+            frames.commands,
+            vec![
+                Command { command: "drain".to_string() },
+                Command { command: "reload_config".to_string() },
+            ]
+        );
+    }
+}
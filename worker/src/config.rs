@@ -0,0 +1,726 @@
+use crate::cgroup;
+use crate::load::LoadSource;
+use crate::shed;
+use crate::shutdown;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+
+pub const VALIDATE_COMMAND: &str = "validate-config";
+
+pub fn validate_requested(args: &[String]) -> bool {
+    args.first().map(String::as_str) == Some(VALIDATE_COMMAND)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata(pub Vec<(String, String)>);
+
+impl FromStr for Metadata {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) if key.trim().is_empty() => Err(format!("пустой ключ в {}", pair.trim())),
+                Some((key, value)) => Ok((key.trim().to_string(), value.trim().to_string())),
+                None => Err(format!("ожидается key=value, получено {}", pair.trim())),
+            })
+            .collect::<Result<_, _>>()
+            .map(Metadata)
+    }
+}
+
+impl fmt::Display for Metadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs: Vec<String> = self.0.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+pub struct Origin {
+    pub name: String,
+    pub value: String,
+    pub layer: &'static str,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeConfig {
+    pub master_address: String,
+    pub master_port: u16,
+    pub advertise_address: Option<String>,
+    pub allow_loopback_advertise: bool,
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub port_retry: u16,
+    pub listen_uds: Option<PathBuf>,
+    pub master_source_address: Option<IpAddr>,
+    pub auth_token: Option<String>,
+    pub admin_token: Option<String>,
+    pub register_max_attempts: u32,
+    pub register_backoff_ms: u64,
+    pub deregister_max_attempts: u32,
+    pub deregister_backoff_ms: u64,
+    pub reconnect_backoff_ms: u64,
+    pub reconnect_backoff_max_ms: u64,
+    pub reconnect_jitter: f64,
+    pub backoff_reset_secs: u64,
+    pub quarantine_after_failures: u32,
+    pub dry_run: bool,
+    pub startup_jitter_ms: u64,
+    pub startup_timeout_secs: u64,
+    pub self_check_interval_secs: u64,
+    pub max_uptime_secs: u64,
+    pub warmup_secs: u64,
+    pub capacity: i32,
+    pub capacity_origin: String,
+    pub capacity_per_cpu: i32,
+    pub capacity_per_gib: i32,
+    pub node_id: Option<String>,
+    pub node_id_file: Option<PathBuf>,
+    pub role: String,
+    pub allowed_roles: Option<Vec<String>>,
+    pub load_source: LoadSource,
+    pub load_seed: Option<u64>,
+    pub load_f_decimals: u32,
+    pub max_concurrent_requests: usize,
+    pub http_keepalive_secs: u64,
+    pub request_id_max_len: usize,
+    pub master_max_reply_bytes: usize,
+    pub master_timeout_ms: u64,
+    pub master_connect_retries: u32,
+    pub master_max_commands: usize,
+    pub master_error_history: usize,
+    pub access_log_history: usize,
+    pub master_max_msgs_per_sec: u32,
+    pub live_channel_capacity: usize,
+    pub max_stream_subscribers: usize,
+    pub history_size: usize,
+    pub peak_window_secs: u64,
+    pub enable_dashboard: bool,
+    pub enable_info: bool,
+    pub enable_status: bool,
+    pub enable_history: bool,
+    pub enable_metrics: bool,
+    pub debug_runtime_stats: bool,
+    pub route_prefix: String,
+    pub probe_path: Option<String>,
+    pub probe_body: String,
+    pub probe_status: u16,
+    pub service_name: String,
+    pub root_metadata: Metadata,
+    pub load_shed_tiers: shed::Tiers,
+    pub shutdown_grace_secs: u64,
+    pub drain_timeout_secs: Option<u64>,
+    pub rate_limit_rps: u32,
+    pub rate_limit_burst: Option<u32>,
+    pub rate_limit_status: u16,
+    pub health_degraded_status: u16,
+    pub rate_limit_body: Option<String>,
+    pub log_level: LevelFilter,
+    pub heartbeat_interval_secs: u64,
+    pub info_interval_secs: u64,
+    pub load_interval_secs: u64,
+    pub load_report_interval_secs: u64,
+    pub slow_request_ms: u64,
+}
+
+impl NodeConfig {
+    pub fn load(args: &[String]) -> Result<Self, String> {
+        Self::load_with_origins(args).map(|(config, _)| config)
+    }
+    
+    pub fn load_with_origins(args: &[String]) -> Result<(Self, Vec<Origin>), String> {
+        let source = Source::open(args)?;
+        let config = Self::from_source(&source)?.validated()?;
+        
+        let read = source.read.borrow();
+        if let Some(name) = source.args.keys().find(|name| !read.contains(name.as_str())) {
+            return Err(format!("неизвестный аргумент --{}", name.to_lowercase().replace('_', "-")));
+        }
+        
+        let origins = source
+            .found
+            .take()
+            .into_iter()
+            .map(|(name, (value, layer))| {
+                let value = if name.ends_with("TOKEN") { "***".to_string() } else { value };
+                Origin { name, value, layer }
+            })
+            .collect();
+        Ok((config, origins))
+    }
+    
+    pub fn redacted(&self) -> Self {
+        let mask = |token: &Option<String>| token.as_ref().map(|_| "***".to_string());
+        NodeConfig {
+            auth_token: mask(&self.auth_token),
+            admin_token: mask(&self.admin_token),
+            ..self.clone()
+        }
+    }
+    
+    pub fn check_overrides(args: &[String], overrides: HashMap<String, String>) -> Vec<String> {
+        let mut source = match Source::open(args) {
+            Ok(source) => source,
+            Err(e) => return vec![e],
+        };
+        source.overrides = overrides;
+        
+        let config = match Self::from_source(&source) {
+            Ok(config) => config,
+            Err(e) => return vec![e],
+        };
+        
+        let read = source.read.borrow();
+        let mut problems: Vec<String> = source
+            .overrides
+            .keys()
+            .filter(|name| !read.contains(name.as_str()))
+            .map(|name| format!("неизвестный параметр {}", name))
+            .collect();
+        problems.sort();
+        problems.extend(config.problems());
+        problems
+    }
+    
+    fn from_source(source: &Source) -> Result<Self, String> {
+        let shutdown_grace_secs = source.parse("SHUTDOWN_GRACE_SECS", 8)?;
+        let capacity_per_cpu = source.parse("CAPACITY_PER_CPU", 25)?;
+        let capacity_per_gib = source.parse("CAPACITY_PER_GIB", 0)?;
+        let (capacity, capacity_origin) = match source.get("CAPACITY").as_deref() {
+            Some("auto") => (
+                num_cpus::get() as i32 * capacity_per_cpu,
+                format!("ядер: {}, на ядро: {}", num_cpus::get(), capacity_per_cpu),
+            ),
+            Some("cgroup") => cgroup::capacity(capacity_per_cpu, capacity_per_gib, 100),
+            _ => (source.parse("CAPACITY", 100)?, String::new()),
+        };
+        
+        Ok(NodeConfig {
+            master_address: source.get("MASTER_ADDRESS").unwrap_or_else(|| "master".to_string()),
+            master_port: source.parse("MASTER_PORT", 8081)?,
+            advertise_address: source.get("ADVERTISE_ADDRESS").filter(|value| !value.is_empty()),
+            allow_loopback_advertise: source.parse("ALLOW_LOOPBACK_ADVERTISE", false)?,
+            bind_address: source.parse("BIND_ADDRESS", IpAddr::from([0, 0, 0, 0]))?,
+            port: source.parse("PORT", 9000)?,
+            port_retry: source.parse("PORT_RETRY", 0)?,
+            listen_uds: source.parse_optional("LISTEN_UDS")?,
+            master_source_address: source.parse_optional("MASTER_SOURCE_ADDRESS")?,
+            auth_token: source.secret("AUTH_TOKEN")?,
+            admin_token: source.secret("ADMIN_TOKEN")?,
+            register_max_attempts: source.parse("REGISTER_MAX_ATTEMPTS", 5)?,
+            register_backoff_ms: source.parse("REGISTER_BACKOFF_MS", 1000)?,
+            deregister_max_attempts: source.parse("DEREGISTER_MAX_ATTEMPTS", 3)?,
+            deregister_backoff_ms: source.parse("DEREGISTER_BACKOFF_MS", 200)?,
+            reconnect_backoff_ms: source.parse("RECONNECT_BACKOFF_MS", 1000)?,
+            reconnect_backoff_max_ms: source.parse("RECONNECT_BACKOFF_MAX_MS", 60000)?,
+            reconnect_jitter: source.parse("RECONNECT_JITTER", 0.5)?,
+            backoff_reset_secs: source.parse("BACKOFF_RESET_SECS", 30)?,
+            quarantine_after_failures: source.parse("QUARANTINE_AFTER_FAILURES", 5)?,
+            dry_run: source.parse("DRY_RUN", false)?,
+            startup_jitter_ms: source.parse("STARTUP_JITTER_MS", 3000)?,
+            startup_timeout_secs: source.parse("STARTUP_TIMEOUT_SECS", 120)?,
+            self_check_interval_secs: source.parse("SELF_CHECK_INTERVAL_SECS", 30)?,
+            max_uptime_secs: source.parse("MAX_UPTIME_SECS", 0)?,
+            warmup_secs: source.parse("WARMUP_SECS", 0)?,
+            capacity,
+            capacity_origin,
+            capacity_per_cpu,
+            capacity_per_gib,
+            node_id: source.get("NODE_ID").filter(|value| !value.trim().is_empty()),
+            node_id_file: source.parse_optional("NODE_ID_FILE")?,
+            role: source.get("ROLE").unwrap_or_else(|| "worker".to_string()),
+            allowed_roles: source.get("ALLOWED_ROLES").map(|roles| {
+                roles
+                    .split(',')
+                    .map(|role| role.trim().to_string())
+                    .filter(|role| !role.is_empty())
+                    .collect()
+            }),
+            load_source: source.parse("LOAD_SOURCE", LoadSource::Simulated)?,
+            load_seed: source.parse_optional("LOAD_SEED")?,
+            load_f_decimals: source.parse("LOAD_F_DECIMALS", 2)?,
+            max_concurrent_requests: source.parse("MAX_CONCURRENT_REQUESTS", 100)?,
+            http_keepalive_secs: source.parse("HTTP_KEEPALIVE_SECS", 60)?,
+            request_id_max_len: source.parse("REQUEST_ID_MAX_LEN", 64)?,
+            master_max_reply_bytes: source.parse("MASTER_MAX_REPLY_BYTES", 1024 * 1024)?,
+            master_timeout_ms: source.parse("MASTER_TIMEOUT_MS", 5000)?,
+            master_connect_retries: source.parse("MASTER_CONNECT_RETRIES", 1)?,
+            master_max_commands: source.parse("MASTER_MAX_COMMANDS", 8)?,
+            master_error_history: source.parse("MASTER_ERROR_HISTORY", 50)?,
+            access_log_history: source.parse("ACCESS_LOG_HISTORY", 0)?,
+            master_max_msgs_per_sec: source.parse("MASTER_MAX_MSGS_PER_SEC", 0)?,
+            live_channel_capacity: source.parse("LIVE_CHANNEL_CAPACITY", 16)?,
+            max_stream_subscribers: source.parse("MAX_STREAM_SUBSCRIBERS", 0)?,
+            history_size: source.parse("HISTORY_SIZE", 120)?,
+            peak_window_secs: source.parse("PEAK_WINDOW_SECS", 300)?,
+            enable_dashboard: source.parse("ENABLE_DASHBOARD", false)?,
+            enable_info: source.parse("ENABLE_INFO", true)?,
+            enable_status: source.parse("ENABLE_STATUS", true)?,
+            enable_history: source.parse("ENABLE_HISTORY", true)?,
+            enable_metrics: source.parse("ENABLE_METRICS", true)?,
+            debug_runtime_stats: source.parse("DEBUG_RUNTIME_STATS", false)?,
+            route_prefix: source.get("ROUTE_PREFIX").unwrap_or_default(),
+            probe_path: source.get("PROBE_PATH"),
+            probe_body: source.get("PROBE_BODY").unwrap_or_else(|| "ok".to_string()),
+            probe_status: source.parse("PROBE_STATUS", 200)?,
+            service_name: source.get("SERVICE_NAME").unwrap_or_default(),
+            root_metadata: source.parse("ROOT_METADATA", Metadata::default())?,
+            load_shed_tiers: source.parse("LOAD_SHED_TIERS", shed::Tiers::default())?,
+            shutdown_grace_secs,
+            drain_timeout_secs: source.parse_optional("DRAIN_TIMEOUT_SECS")?,
+            rate_limit_rps: source.parse("RATE_LIMIT_RPS", 0)?,
+            rate_limit_burst: source.parse_optional("RATE_LIMIT_BURST")?,
+            rate_limit_status: source.parse("RATE_LIMIT_STATUS", 429)?,
+            health_degraded_status: source.parse("HEALTH_DEGRADED_STATUS", 200)?,
+            rate_limit_body: source.get("RATE_LIMIT_BODY").filter(|value| !value.is_empty()),
+            log_level: source.parse("LOG_LEVEL", LevelFilter::INFO)?,
+            heartbeat_interval_secs: source.parse("HEARTBEAT_INTERVAL_SECS", 10)?,
+            info_interval_secs: source.parse("INFO_INTERVAL_SECS", 60)?,
+            load_interval_secs: source.parse("LOAD_INTERVAL_SECS", 5)?,
+            load_report_interval_secs: source.parse("LOAD_REPORT_INTERVAL_SECS", 0)?,
+            slow_request_ms: source.parse("SLOW_REQUEST_MS", 1000)?,
+        })
+    }
+    
+    fn validated(self) -> Result<Self, String> {
+        let problems = self.problems();
+        if !problems.is_empty() {
+            return Err(problems.join("; "));
+        }
+        
+        Ok(self)
+    }
+    
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        
+        #[cfg(not(unix))]
+        if self.listen_uds.is_some() {
+            problems.push("LISTEN_UDS поддерживается только на Unix".to_string());
+        }
+        if self.register_max_attempts == 0 {
+            problems.push("REGISTER_MAX_ATTEMPTS должен быть не меньше 1".to_string());
+        }
+        if self.deregister_max_attempts == 0 {
+            problems.push("DEREGISTER_MAX_ATTEMPTS должен быть не меньше 1".to_string());
+        }
+        if self.capacity <= 0 {
+            problems.push("CAPACITY должен быть больше 0".to_string());
+        }
+        if !self.route_prefix.is_empty() && (!self.route_prefix.starts_with('/') || self.route_prefix.ends_with('/')) {
+            problems.push(format!("ROUTE_PREFIX={} должен начинаться с / и не заканчиваться на /", self.route_prefix));
+        }
+        if self.load_f_decimals > 6 {
+            problems.push("LOAD_F_DECIMALS должен быть от 0 до 6".to_string());
+        }
+        if self.live_channel_capacity == 0 {
+            problems.push("LIVE_CHANNEL_CAPACITY должен быть не меньше 1".to_string());
+        }
+        if self.capacity_per_cpu <= 0 {
+            problems.push("CAPACITY_PER_CPU должен быть больше 0".to_string());
+        }
+        if self.capacity_per_gib < 0 {
+            problems.push("CAPACITY_PER_GIB не может быть отрицательным".to_string());
+        }
+        if self.role.trim().is_empty() {
+            problems.push("ROLE не может быть пустым".to_string());
+        }
+        if let Some(allowed) = &self.allowed_roles {
+            if !allowed.contains(&self.role) {
+                problems.push(format!("ROLE={} не входит в ALLOWED_ROLES ({})", self.role, allowed.join(", ")));
+            }
+        }
+        if let Some(address) = self.advertise_address.as_deref().filter(|_| !self.allow_loopback_advertise) {
+            let unroutable = match address.parse::<IpAddr>() {
+                Ok(ip) => ip.is_unspecified() || ip.is_loopback(),
+                Err(_) => address.eq_ignore_ascii_case("localhost"),
+            };
+            if unroutable {
+                problems.push(format!(
+                    "ADVERTISE_ADDRESS={}: мастер не сможет подключиться по этому адресу; задайте маршрутизируемый адрес или ALLOW_LOOPBACK_ADVERTISE=true",
+                    address
+                ));
+            }
+        }
+        if self.port == 0 {
+            problems.push("PORT=0: порт должен быть от 1 до 65535".to_string());
+        }
+        if self.port.checked_add(self.port_retry).is_none() {
+            problems.push(format!("PORT={} и PORT_RETRY={} выходят за 65535", self.port, self.port_retry));
+        }
+        if self.max_concurrent_requests == 0 {
+            problems.push("MAX_CONCURRENT_REQUESTS должен быть не меньше 1".to_string());
+        }
+        for (name, value) in [
+            ("HEARTBEAT_INTERVAL_SECS", self.heartbeat_interval_secs),
+            ("INFO_INTERVAL_SECS", self.info_interval_secs),
+            ("LOAD_INTERVAL_SECS", self.load_interval_secs),
+            ("SHUTDOWN_GRACE_SECS", self.shutdown_grace_secs),
+            ("STARTUP_TIMEOUT_SECS", self.startup_timeout_secs),
+        ] {
+            if value == 0 {
+                problems.push(format!("{}=0: интервал должен быть не меньше 1 секунды", name));
+            }
+        }
+        if let Some(drain_timeout_secs) = self.drain_timeout_secs {
+            let share = shutdown::drain_share(Duration::from_secs(self.shutdown_grace_secs));
+            if Duration::from_secs(drain_timeout_secs) > share {
+                problems.push(format!(
+                    "DRAIN_TIMEOUT_SECS={} больше доли дренажа в SHUTDOWN_GRACE_SECS={} ({} мс)",
+                    drain_timeout_secs,
+                    self.shutdown_grace_secs,
+                    share.as_millis()
+                ));
+            }
+        }
+        if self.master_timeout_ms == 0 {
+            problems.push("MASTER_TIMEOUT_MS=0: таймаут обмена с мастером должен быть не меньше 1 мс".to_string());
+        }
+        if self.reconnect_backoff_ms == 0 {
+            problems.push("RECONNECT_BACKOFF_MS=0: пауза переподключения должна быть не меньше 1 мс".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.reconnect_jitter) {
+            problems.push(format!("RECONNECT_JITTER={} должен быть от 0 до 1", self.reconnect_jitter));
+        }
+        if self.reconnect_backoff_max_ms < self.reconnect_backoff_ms {
+            problems.push(format!(
+                "RECONNECT_BACKOFF_MAX_MS={} меньше RECONNECT_BACKOFF_MS={}",
+                self.reconnect_backoff_max_ms, self.reconnect_backoff_ms
+            ));
+        }
+        if self.rate_limit_status != 429 && self.rate_limit_status != 503 {
+            problems.push("RATE_LIMIT_STATUS должен быть 429 или 503".to_string());
+        }
+        for path in self.load_shed_tiers.protected_paths() {
+            problems.push(format!("LOAD_SHED_TIERS: {} должен оставаться доступным при перегрузке", path));
+        }
+        if let Some(path) = &self.probe_path {
+            if !path.starts_with('/') || path.contains([':', '*', '{', '}']) {
+                problems.push(format!("PROBE_PATH={} должен начинаться с / и не содержать : * {{ }}", path));
+            } else if is_builtin_route(path) {
+                problems.push(format!("PROBE_PATH={} совпадает со встроенным маршрутом", path));
+            }
+        }
+        if !(100..=599).contains(&self.probe_status) {
+            problems.push(format!("PROBE_STATUS={}: ожидается HTTP-статус 100..=599", self.probe_status));
+        }
+        if self.health_degraded_status != 200 && self.health_degraded_status != 503 {
+            problems.push("HEALTH_DEGRADED_STATUS должен быть 200 или 503".to_string());
+        }
+        if self.rate_limit_burst == Some(0) {
+            problems.push("RATE_LIMIT_BURST должен быть не меньше 1".to_string());
+        }
+        if let Some(body) = &self.rate_limit_body {
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(body) {
+                problems.push(format!("RATE_LIMIT_BODY не является корректным JSON: {}", e));
+            }
+        }
+        
+        problems
+    }
+    
+    pub fn reloaded(&self, fresh: NodeConfig) -> (NodeConfig, Vec<String>) {
+        let mut next = self.clone();
+        let mut changes = Vec::new();
+        
+        macro_rules! reloadable {
+            ($($field:ident),*) => {$(
+                if self.$field != fresh.$field {
+                    changes.push(format!("{}: {} → {}", stringify!($field).to_uppercase(), self.$field, fresh.$field));
+                    next.$field = fresh.$field;
+                }
+            )*};
+        }
+        
+        macro_rules! immutable {
+            ($($field:ident),*) => {$(
+                if self.$field != fresh.$field {
+                    warn!("⚠️ {} меняется только перезапуском, новое значение проигнорировано", stringify!($field).to_uppercase());
+                }
+            )*};
+        }
+        
+        reloadable!(
+            log_level, enable_dashboard, capacity, capacity_origin, capacity_per_cpu, capacity_per_gib,
+            heartbeat_interval_secs, info_interval_secs, load_interval_secs, load_report_interval_secs,
+            shutdown_grace_secs, deregister_max_attempts, deregister_backoff_ms, quarantine_after_failures,
+            reconnect_jitter,
+            load_f_decimals, service_name, root_metadata, load_shed_tiers, slow_request_ms,
+            master_max_commands, debug_runtime_stats, max_stream_subscribers
+        );
+        if self.drain_timeout_secs != fresh.drain_timeout_secs {
+            let show = |secs: Option<u64>| secs.map_or_else(|| "доля шага".to_string(), |secs| secs.to_string());
+            changes.push(format!(
+                "DRAIN_TIMEOUT_SECS: {} → {}",
+                show(self.drain_timeout_secs),
+                show(fresh.drain_timeout_secs)
+            ));
+            next.drain_timeout_secs = fresh.drain_timeout_secs;
+        }
+        immutable!(
+            master_address, master_port, enable_info, enable_status, enable_history, enable_metrics,
+            advertise_address, allow_loopback_advertise, bind_address, port, port_retry, listen_uds,
+            master_source_address, auth_token, admin_token, register_max_attempts, register_backoff_ms,
+            reconnect_backoff_ms, reconnect_backoff_max_ms, backoff_reset_secs, dry_run,
+            startup_jitter_ms, startup_timeout_secs, self_check_interval_secs, max_uptime_secs,
+            warmup_secs, node_id, node_id_file, role, allowed_roles, load_source, load_seed,
+            max_concurrent_requests, http_keepalive_secs, request_id_max_len, master_max_reply_bytes,
+            master_timeout_ms, master_connect_retries, master_error_history, access_log_history,
+            master_max_msgs_per_sec, live_channel_capacity, history_size, peak_window_secs,
+            route_prefix, probe_path, probe_body, probe_status, rate_limit_rps, rate_limit_burst,
+            rate_limit_status, health_degraded_status, rate_limit_body
+        );
+        
+        (next, changes)
+    }
+}
+
+pub struct SharedConfig(RwLock<Arc<NodeConfig>>);
+
+impl SharedConfig {
+    pub fn new(config: NodeConfig) -> Self {
+        SharedConfig(RwLock::new(Arc::new(config)))
+    }
+    
+    pub fn get(&self) -> Arc<NodeConfig> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+    
+    pub fn replace(&self, config: NodeConfig) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(config);
+    }
+}
+
+struct Source {
+    file: HashMap<String, String>,
+    env: HashMap<String, String>,
+    args: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+    read: RefCell<HashSet<String>>,
+    found: RefCell<BTreeMap<String, (String, &'static str)>>,
+}
+
+fn is_builtin_route(path: &str) -> bool {
+    #[cfg(feature = "extensions")]
+    if path == crate::EXTENSION_PATH || path.starts_with(&format!("{}/", crate::EXTENSION_PATH)) {
+        return true;
+    }
+    
+    path == "/" || path == "/api" || path.starts_with("/api/") || path == "/metrics"
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<HashMap<String, String>, String> {
+    let mut parsed = HashMap::new();
+    for arg in args {
+        let Some(option) = arg.strip_prefix("--") else {
+            return Err(format!("неожиданный аргумент {}, ожидается --name=value", arg));
+        };
+        
+        let (name, value) = option.split_once('=').unwrap_or((option, "true"));
+        parsed.insert(name.to_uppercase().replace('-', "_"), value.to_string());
+    }
+    
+    Ok(parsed)
+}
+
+impl Source {
+    fn open(args: &[String]) -> Result<Self, String> {
+        let args = parse_args(args.iter().skip_while(|arg| *arg == VALIDATE_COMMAND).cloned())?;
+        let env: HashMap<String, String> = env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+        let Some(path) = env.get("CONFIG_FILE").cloned() else {
+            return Ok(Source::with_layers(HashMap::new(), env, args));
+        };
+        
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("не удалось прочитать CONFIG_FILE ({}): {}", path, e))?;
+        let mut file = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("{}:{}: ожидается KEY=VALUE", path, number + 1))?;
+            file.insert(key.trim().to_string(), value.trim().to_string());
+        }
+        
+        Ok(Source::with_layers(file, env, args))
+    }
+    
+    fn with_layers(file: HashMap<String, String>, env: HashMap<String, String>, args: HashMap<String, String>) -> Self {
+        Source {
+            file,
+            env,
+            args,
+            overrides: HashMap::new(),
+            read: RefCell::new(HashSet::new()),
+            found: RefCell::new(BTreeMap::new()),
+        }
+    }
+    
+    fn get(&self, name: &str) -> Option<String> {
+        self.read.borrow_mut().insert(name.to_string());
+        let (value, layer) = self
+            .overrides
+            .get(name)
+            .map(|value| (value.clone(), "проверка"))
+            .or_else(|| self.args.get(name).map(|value| (value.clone(), "аргументы")))
+            .or_else(|| self.env.get(name).map(|value| (value.clone(), "окружение")))
+            .or_else(|| self.file.get(name).map(|value| (value.clone(), "CONFIG_FILE")))?;
+        
+        self.found.borrow_mut().insert(name.to_string(), (value.clone(), layer));
+        Some(value)
+    }
+    
+    fn parse<T: FromStr>(&self, name: &str, default: T) -> Result<T, String>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.get(name) {
+            Some(value) => value
+                .parse()
+                .map_err(|e| format!("некорректное значение {}={}: {}", name, value, e)),
+            None => Ok(default),
+        }
+    }
+    
+    fn parse_optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.get(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|e| format!("некорректное значение {}={}: {}", name, value, e)),
+            None => Ok(None),
+        }
+    }
+    
+    fn secret(&self, name: &str) -> Result<Option<String>, String> {
+        let file_var = format!("{}_FILE", name);
+        
+        if let Some(path) = self.get(&file_var) {
+            if self.get(name).is_some() {
+                warn!("⚠️ Заданы и {}, и {}: используется {}", name, file_var, file_var);
+            }
+            
+            let contents = fs::read_to_string(&path)
+                .map_err(|e| format!("не удалось прочитать {} ({}): {}", file_var, path, e))?;
+            let secret = contents.trim_end_matches(['\n', '\r']);
+            if secret.is_empty() {
+                return Err(format!("файл секрета {} ({}) пуст", file_var, path));
+            }
+            
+            return Ok(Some(secret.to_string()));
+        }
+        
+        Ok(self.get(name).filter(|value| !value.is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn layer(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+    
+    fn port(file: &[(&str, &str)], env: &[(&str, &str)], args: &[(&str, &str)]) -> (u16, Option<&'static str>) {
+        let source = Source::with_layers(layer(file), layer(env), layer(args));
+        let config = NodeConfig::from_source(&source).unwrap();
+        let origin = source.found.borrow().get("MASTER_PORT").map(|(_, layer)| *layer);
+        (config.master_port, origin)
+    }
+    
+    #[test]
+    fn layers_override_in_order() {
+        let file = [("MASTER_PORT", "1001")];
+        let env = [("MASTER_PORT", "1002")];
+        let args = [("MASTER_PORT", "1003")];
+        
+        assert_eq!(port(&[], &[], &[]), (8081, None));
+        assert_eq!(port(&file, &[], &[]), (1001, Some("CONFIG_FILE")));
+        assert_eq!(port(&file, &env, &[]), (1002, Some("окружение")));
+        assert_eq!(port(&file, &env, &args), (1003, Some("аргументы")));
+        assert_eq!(port(&[], &[], &args), (1003, Some("аргументы")));
+    }
+    
+    fn load_error(name: &str, value: &str) -> String {
+        let source = Source::with_layers(HashMap::new(), layer(&[(name, value)]), HashMap::new());
+        NodeConfig::from_source(&source).unwrap_err()
+    }
+    
+    #[test]
+    fn parse_errors_name_the_variable_and_value() {
+        for (name, value, detail) in [
+            ("ROOT_METADATA", "foo", "ожидается key=value, получено foo"),
+            ("ROOT_METADATA", "=x", "пустой ключ в =x"),
+            ("MASTER_PORT", "abc", "invalid digit"),
+            ("LOG_LEVEL", "loud", "error parsing level filter"),
+        ] {
+            let error = load_error(name, value);
+            assert!(error.contains(&format!("{}={}", name, value)), "{}", error);
+            assert!(error.contains(detail), "{}", error);
+        }
+    }
+    
+    #[test]
+    fn drain_timeout_must_fit_the_drain_step() {
+        let problems = |env: &[(&str, &str)]| {
+            let source = Source::with_layers(HashMap::new(), layer(env), HashMap::new());
+            NodeConfig::from_source(&source).unwrap().problems()
+        };
+        
+        assert!(problems(&[]).is_empty());
+        assert!(problems(&[("SHUTDOWN_GRACE_SECS", "8"), ("DRAIN_TIMEOUT_SECS", "4")]).is_empty());
+        let rejected = problems(&[("SHUTDOWN_GRACE_SECS", "8"), ("DRAIN_TIMEOUT_SECS", "5")]);
+        assert!(rejected.iter().any(|problem| problem.starts_with("DRAIN_TIMEOUT_SECS=5")), "{:?}", rejected);
+    }
+    
+    #[test]
+    fn zero_intervals_are_rejected() {
+        for name in ["HEARTBEAT_INTERVAL_SECS", "LOAD_INTERVAL_SECS"] {
+            let source = Source::with_layers(HashMap::new(), layer(&[(name, "0")]), HashMap::new());
+            let problems = NodeConfig::from_source(&source).unwrap().problems();
+            let expected = format!("{}=0: интервал должен быть не меньше 1 секунды", name);
+            assert!(problems.contains(&expected), "{:?}", problems);
+        }
+    }
+    
+    #[test]
+    fn parses_args_into_names() {
+        let args = ["--master-port=1003", "--dry-run"].map(String::from);
+        let parsed = parse_args(args.into_iter()).unwrap();
+        assert_eq!(parsed.get("MASTER_PORT").map(String::as_str), Some("1003"));
+        assert_eq!(parsed.get("DRY_RUN").map(String::as_str), Some("true"));
+        assert!(parse_args(["master-port=1".to_string()].into_iter()).is_err());
+    }
+    
+    #[test]
+    fn validate_command_is_not_an_option() {
+        let args = [VALIDATE_COMMAND, "--master-port=1003"].map(String::from);
+        assert!(validate_requested(&args));
+        assert!(!validate_requested(&args[1..]));
+        
+        let source = Source::open(&args).unwrap();
+        assert_eq!(source.args.get("MASTER_PORT").map(String::as_str), Some("1003"));
+    }
+}
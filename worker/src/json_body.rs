@@ -0,0 +1,87 @@
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use tracing::warn;
+
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+    
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let path = request.uri().path().to_string();
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(ValidJson(value)),
+            Err(rejection) => {
+                let status = match rejection {
+                    JsonRejection::MissingJsonContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    _ => StatusCode::BAD_REQUEST,
+                };
+                let detail = rejection.body_text();
+                warn!("⚠️ Некорректное тело запроса {}: {}", path, detail);
+                let body = json!({ "error": "invalid json", "detail": detail });
+                Err((status, Json(body)).into_response())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::header, routing::post, Router};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+    
+    #[derive(Deserialize)]
+    struct Payload {
+        #[allow(dead_code)]
+        load: f32,
+    }
+    
+    async fn send(content_type: &str, body: &'static str) -> (StatusCode, serde_json::Value) {
+        let app = Router::new().route("/", post(|ValidJson(_): ValidJson<Payload>| async {}));
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+    
+    #[tokio::test]
+    async fn rejects_malformed_json() {
+        let (status, body) = send("application/json", "{\"load\":").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid json");
+        assert!(body["detail"].as_str().unwrap().contains("EOF"), "{}", body);
+    }
+    
+    #[tokio::test]
+    async fn rejects_wrong_content_type() {
+        let (status, body) = send("text/plain", "{\"load\":1}").await;
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(body["error"], "invalid json");
+        assert!(body["detail"].as_str().unwrap().contains("Content-Type"), "{}", body);
+    }
+    
+    #[tokio::test]
+    async fn rejects_schema_mismatch() {
+        let (status, body) = send("application/json", "{\"load\":\"x\"}").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid json");
+        let detail = body["detail"].as_str().unwrap();
+        assert!(detail.contains("load: invalid type: string \"x\", expected f32"), "{}", detail);
+    }
+}
@@ -0,0 +1,2619 @@
+use access_log::{AccessLog, AccessRecord};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::{self, Next},
+    http::{header, HeaderMap, StatusCode},
+    response::sse::{KeepAlive, Sse},
+    response::{Html, IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use codec::{Codec, Frames};
+use config::{NodeConfig, SharedConfig};
+use json_body::ValidJson;
+use link::MasterLink;
+use live::{LiveLoad, LoadSample, SubscribeError};
+use load::{LoadProvider, LoadSource};
+use master_errors::{MasterError, MasterErrorLog};
+use metrics::{MessageKind, Metrics, Rates};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError};
+use tokio::net::{lookup_host, TcpSocket, TcpStream, UdpSocket};
+use tokio::sync::{watch, Mutex, Notify};
+use rand::Rng;
+use tokio::time::{interval_at, Duration, Instant, Interval, MissedTickBehavior, sleep};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::task::JoinHandle;
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tower_http::cors::CorsLayer;
+use request_id::RequestId;
+use supervisor::{Supervisor, TaskRestarts};
+use tower_http::trace::TraceLayer;
+use tracing::{debug, info, error, warn};
+
+mod access_log;
+mod auth;
+mod cgroup;
+mod codec;
+mod config;
+mod json_body;
+mod link;
+mod live;
+mod load;
+mod master_errors;
+mod metrics;
+mod node_id;
+mod rate_limit;
+mod request_id;
+mod self_check;
+mod server;
+mod shed;
+mod shutdown;
+mod supervisor;
+mod systemd;
+mod telemetry;
+
+#[derive(Clone)]
+struct NodeState {
+    id: String,
+    generation: u64,
+    port: u16,
+    load: Arc<Mutex<i32>>,
+    load_f: Arc<Mutex<f32>>,
+    registered: Arc<AtomicBool>,
+    stopping: Arc<AtomicBool>,
+    over_capacity: Arc<AtomicBool>,
+    quarantined: Arc<AtomicBool>,
+    load_paused: Arc<AtomicBool>,
+    listener_error: Arc<std::sync::Mutex<Option<String>>>,
+    warmed_up: Arc<AtomicBool>,
+    protocol_version: Arc<AtomicU32>,
+    draining: Arc<AtomicBool>,
+    drain_transition: Arc<Mutex<()>>,
+    reconnecting: Arc<Mutex<()>>,
+    shutdown_requested: Arc<Notify>,
+    reload_requested: Arc<Notify>,
+    supervisor: Arc<Supervisor>,
+    last_heartbeat_at: Arc<AtomicU64>,
+    active_connections: Arc<AtomicUsize>,
+    load_provider: Arc<LoadProvider>,
+    metrics: Arc<Metrics>,
+    link: Arc<MasterLink>,
+    master_errors: Arc<MasterErrorLog>,
+    access_log: Arc<AccessLog>,
+    live: Arc<LiveLoad>,
+    rate_limiter: Arc<rate_limit::RateLimiter>,
+    master_limiter: Arc<rate_limit::RateLimiter>,
+    config: Arc<SharedConfig>,
+    args: Arc<Vec<String>>,
+}
+
+impl NodeState {
+    fn new(id: String, port: u16, config: NodeConfig, args: Vec<String>) -> Self {
+        let active_connections = Arc::new(AtomicUsize::new(0));
+        let load_provider = LoadProvider::new(
+            config.load_source,
+            active_connections.clone(),
+            config.max_concurrent_requests,
+            config.load_seed,
+        );
+        
+        NodeState {
+            id,
+            generation: node_id::generation(),
+            port,
+            load: Arc::new(Mutex::new(0)),
+            load_f: Arc::new(Mutex::new(0.0)),
+            registered: Arc::new(AtomicBool::new(false)),
+            stopping: Arc::new(AtomicBool::new(false)),
+            over_capacity: Arc::new(AtomicBool::new(false)),
+            quarantined: Arc::new(AtomicBool::new(false)),
+            load_paused: Arc::new(AtomicBool::new(false)),
+            listener_error: Arc::new(std::sync::Mutex::new(None)),
+            warmed_up: Arc::new(AtomicBool::new(false)),
+            protocol_version: Arc::new(AtomicU32::new(PROTOCOL_VERSION)),
+            draining: Arc::new(AtomicBool::new(false)),
+            drain_transition: Arc::new(Mutex::new(())),
+            reconnecting: Arc::new(Mutex::new(())),
+            shutdown_requested: Arc::new(Notify::new()),
+            reload_requested: Arc::new(Notify::new()),
+            supervisor: Arc::new(Supervisor::default()),
+            last_heartbeat_at: Arc::new(AtomicU64::new(0)),
+            active_connections,
+            load_provider: Arc::new(load_provider),
+            metrics: Arc::new(Metrics::default()),
+            link: Arc::new(MasterLink::new(
+                Duration::from_millis(config.reconnect_backoff_ms),
+                Duration::from_millis(config.reconnect_backoff_max_ms),
+                Duration::from_secs(config.backoff_reset_secs),
+            )),
+            master_errors: Arc::new(MasterErrorLog::new(config.master_error_history)),
+            access_log: Arc::new(AccessLog::new(config.access_log_history)),
+            live: Arc::new(LiveLoad::new(config.live_channel_capacity, config.history_size)),
+            rate_limiter: Arc::new(rate_limit::RateLimiter::new(
+                config.rate_limit_rps,
+                config.rate_limit_burst.unwrap_or(config.rate_limit_rps),
+            )),
+            master_limiter: Arc::new(rate_limit::RateLimiter::new(
+                config.master_max_msgs_per_sec,
+                config.master_max_msgs_per_sec,
+            )),
+            config: Arc::new(SharedConfig::new(config)),
+            args: Arc::new(args),
+        }
+    }
+    
+    fn config(&self) -> Arc<NodeConfig> {
+        self.config.get()
+    }
+    
+    // Tokio mutexes do not poison and unlock on unwind, and these sections only copy values.
+    // `load` is always locked before `load_f`, so readers get both from the same sample.
+    async fn load_pair(&self) -> (i32, f32) {
+        let load = self.load.lock().await;
+        let load_f = self.load_f.lock().await;
+        (*load, *load_f)
+    }
+    
+    async fn set_load_pair(&self, value: i32, value_f: f32) {
+        let mut load = self.load.lock().await;
+        let mut load_f = self.load_f.lock().await;
+        *load = value;
+        *load_f = value_f;
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RegisterMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    address: String,
+    port: u16,
+    role: String,
+    capacity: i32,
+    generation: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HeartbeatMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uptime: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_connections: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LoadUpdateMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    load: i32,
+    load_f: f32,
+    load_percent: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeregisterMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StatusUpdateMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct InfoMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+    role: String,
+    capacity: i32,
+    worker_version: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    service_name: String,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PingMessage {
+    #[serde(rename = "type")]
+    message_type: String,
+    id: String,
+}
+
+#[derive(Serialize)]
+struct DrainResponse {
+    status: String,
+    changed: bool,
+    master_notified: bool,
+}
+
+#[derive(Serialize)]
+struct HeartbeatNowResponse {
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ReconnectResponse {
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MasterPingResponse {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rtt_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SetLoadRequest {
+    load: f32,
+}
+
+#[derive(Serialize)]
+struct LoadPauseResponse {
+    paused: bool,
+    changed: bool,
+    load: i32,
+}
+
+#[derive(Serialize)]
+struct SetLoadResponse {
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ServerResponse {
+    status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    negotiated_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    retry_after_ms: Option<u64>,
+    #[serde(skip)]
+    commands: Vec<MasterCommand>,
+}
+
+#[derive(Deserialize)]
+struct MasterCommand {
+    command: String,
+    #[serde(default)]
+    token: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: String,
+    node_id: String,
+    load: i32,
+    uptime: u64,
+    uptime_human: String,
+    checks: HealthChecks,
+    failing: Vec<FailingCheck>,
+}
+
+#[derive(Serialize)]
+struct HealthChecks {
+    http_listener: &'static str,
+    master: &'static str,
+}
+
+#[derive(Serialize)]
+struct FailingCheck {
+    check: &'static str,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct ReadyResponse {
+    ready: bool,
+    status: &'static str,
+}
+
+#[derive(Serialize)]
+struct InfoResponse {
+    node_id: String,
+    generation: u64,
+    port: u16,
+    role: String,
+    load: i32,
+    capacity: i32,
+    master_address: String,
+    protocol_version: u32,
+}
+
+#[derive(Serialize)]
+struct StatusResponse {
+    status: String,
+    node_id: String,
+    load: i32,
+    load_f: f32,
+    capacity: i32,
+    headroom: i32,
+    active_connections: usize,
+    registered: bool,
+    peak_load: i32,
+    load_paused: bool,
+    stream_subscribers: usize,
+}
+
+#[derive(Serialize)]
+struct DebugStateResponse {
+    node_id: String,
+    load: i32,
+    load_source: String,
+    active_connections: usize,
+    max_concurrent_requests: usize,
+    registered: bool,
+    ready: bool,
+    draining: bool,
+    quarantined: bool,
+    last_heartbeat_at: Option<u64>,
+    master_endpoint: String,
+    master_connected: bool,
+    reconnect_backoff_ms: u128,
+    last_master_contact_secs_ago: Option<u64>,
+    dry_run: bool,
+    uptime: u64,
+    task_restarts: TaskRestarts,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    runtime: Option<RuntimeStats>,
+}
+
+#[derive(Serialize)]
+struct RuntimeStats {
+    alive_tasks: usize,
+    worker_threads: usize,
+}
+
+#[derive(Serialize)]
+struct ConfigValidationResponse {
+    valid: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct MasterErrorsResponse {
+    limit: usize,
+    errors: Vec<MasterError>,
+}
+
+#[derive(Serialize)]
+struct AccessLogResponse {
+    limit: usize,
+    requests: Vec<AccessRecord>,
+}
+
+#[derive(Serialize)]
+struct TopologyResponse {
+    masters: Vec<String>,
+    active_master: Option<String>,
+    connected: bool,
+    last_success_at: Option<u64>,
+    protocol_version: u32,
+}
+
+const PROTOCOL_VERSION: u32 = 2;
+const CODEC: codec::Json = codec::Json;
+
+fn encode<T: Serialize>(state: &NodeState, message: &T, version: u32) -> Result<String, String> {
+    if !state.config().enable_metrics {
+        return CODEC.encode(message, version);
+    }
+    
+    let started = Instant::now();
+    let encoded = CODEC.encode(message, version);
+    state.metrics.record_encode(started.elapsed());
+    encoded
+}
+const MAX_UPTIME_JITTER: f64 = 0.1;
+const RESTART_EXIT_CODE: i32 = 75;
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(100);
+#[cfg(feature = "extensions")]
+pub const EXTENSION_PATH: &str = "/ext";
+
+static STARTED: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+static CLOCK_WARNED: AtomicBool = AtomicBool::new(false);
+
+fn unix_time() -> u64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(elapsed) => elapsed.as_secs(),
+        Err(e) => {
+            if !CLOCK_WARNED.swap(true, Ordering::Relaxed) {
+                warn!("⚠️ Системные часы раньше UNIX-эпохи ({}), время считается равным 0", e);
+            }
+            0
+        }
+    }
+}
+
+fn get_uptime() -> u64 {
+    STARTED.get().map_or(0, |started| started.elapsed().as_secs())
+}
+
+fn format_uptime(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+fn random_jitter(max_ms: u64) -> Duration {
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    
+    Duration::from_millis(rand::thread_rng().gen_range(0..=max_ms))
+}
+
+fn jittered(delay: Duration, fraction: f64) -> Duration {
+    jittered_with(&mut rand::thread_rng(), delay, fraction)
+}
+
+fn jittered_with(rng: &mut impl Rng, delay: Duration, fraction: f64) -> Duration {
+    if fraction == 0.0 {
+        return delay;
+    }
+    
+    delay.mul_f64(1.0 - fraction * rng.gen::<f64>())
+}
+
+async fn connect_with_retry(config: &NodeConfig) -> std::io::Result<TcpStream> {
+    let mut retries = config.master_connect_retries;
+    loop {
+        match connect_to_master(config).await {
+            Err(e) if retries > 0 => {
+                debug!("Мастер не принял соединение ({}), повтор через {} мс", e, CONNECT_RETRY_DELAY.as_millis());
+                retries -= 1;
+                sleep(CONNECT_RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn connect_to_master(config: &NodeConfig) -> std::io::Result<TcpStream> {
+    let target = (config.master_address.as_str(), config.master_port);
+    let Some(source) = config.master_source_address else {
+        return TcpStream::connect(target).await;
+    };
+    
+    let mut last_error = None;
+    for addr in lookup_host(target).await?.filter(|addr| addr.is_ipv4() == source.is_ipv4()) {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        socket.bind(SocketAddr::new(source, 0))?;
+        match socket.connect(addr).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    
+    Err(last_error.unwrap_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("у мастера нет адреса того же семейства, что и {}", source),
+        )
+    }))
+}
+
+enum MasterWait {
+    Ready,
+    ShutdownRequested,
+}
+
+async fn wait_for_master(config: &NodeConfig) -> Result<MasterWait, Box<dyn std::error::Error>> {
+    let mut attempts = 0;
+    let max_attempts = 30;
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    
+    while attempts < max_attempts {
+        let attempt = tokio::select! {
+            attempt = connect_to_master(config) => attempt,
+            _ = &mut shutdown => return Ok(MasterWait::ShutdownRequested),
+        };
+        
+        match attempt {
+            Ok(_) => {
+                info!("✅ Мастер готов!");
+                return Ok(MasterWait::Ready);
+            }
+            Err(e) => {
+                attempts += 1;
+                info!("⏳ Ожидание мастера... (попытка {}/{}): {}", attempts, max_attempts, e);
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(2)) => {}
+                    _ = &mut shutdown => return Ok(MasterWait::ShutdownRequested),
+                }
+            }
+        }
+    }
+    
+    Err("Мастер не готов после всех попыток".into())
+}
+
+async fn within_startup<T>(deadline: Instant, stage: impl std::future::Future<Output = T>) -> T {
+    match tokio::time::timeout_at(deadline, stage).await {
+        Ok(output) => output,
+        Err(_) => {
+            error!("💀 Нода не запустилась за STARTUP_TIMEOUT_SECS, завершаю процесс");
+            std::process::exit(1);
+        }
+    }
+}
+
+enum MasterReply {
+    DryRun,
+    Empty,
+    Response(ServerResponse),
+}
+
+fn parse_reply(reply: &[u8]) -> Result<MasterReply, Box<dyn std::error::Error>> {
+    if reply.is_empty() {
+        return Ok(MasterReply::Empty);
+    }
+    
+    let text = std::str::from_utf8(reply)
+        .map_err(|e| format!("ответ мастера не в UTF-8: {}", e))?;
+    info!("Ответ от мастера: {}", text);
+    
+    let Some(Frames { reply: mut response, commands }) = CODEC.decode::<ServerResponse, MasterCommand>(text)? else {
+        return Ok(MasterReply::Empty);
+    };
+    
+    if let Some(command) = response.command.take() {
+        response.commands.push(MasterCommand { command, token: response.token.take() });
+    }
+    response.commands.extend(commands);
+    Ok(MasterReply::Response(response))
+}
+
+async fn detect_local_address(config: &NodeConfig) -> Result<IpAddr, Box<dyn std::error::Error>> {
+    let target = lookup_host((config.master_address.as_str(), config.master_port))
+        .await?
+        .next()
+        .ok_or("адрес мастера не разрешается")?;
+    let bind: SocketAddr = if target.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        (std::net::Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(target).await?;
+    let ip = socket.local_addr()?.ip();
+    if ip.is_unspecified() {
+        return Err("система не выбрала исходящий интерфейс".into());
+    }
+    
+    Ok(ip)
+}
+
+async fn advertise_address(config: &NodeConfig) -> Result<String, Box<dyn std::error::Error>> {
+    if let Some(address) = &config.advertise_address {
+        let unspecified = address.parse::<IpAddr>().map(|ip| ip.is_unspecified()).unwrap_or(false);
+        if !unspecified {
+            return Ok(address.clone());
+        }
+    }
+    
+    if !config.bind_address.is_unspecified() && !config.bind_address.is_loopback() {
+        return Ok(config.bind_address.to_string());
+    }
+    if config.dry_run {
+        return Ok(config.advertise_address.clone().unwrap_or_else(|| config.bind_address.to_string()));
+    }
+    
+    match detect_local_address(config).await {
+        Ok(ip) => Ok(ip.to_string()),
+        Err(e) => Err(format!(
+            "не удалось определить адрес для регистрации ({}); задайте ADVERTISE_ADDRESS",
+            e
+        )
+        .into()),
+    }
+}
+
+#[tracing::instrument(name = "master_send", skip(state, message), fields(kind = kind.as_str()))]
+async fn send_to_master(
+    state: &NodeState,
+    kind: MessageKind,
+    message: &str,
+) -> Result<MasterReply, Box<dyn std::error::Error>> {
+    if state.config().dry_run {
+        info!("🧪 [dry-run] Сообщение мастеру: {}", message);
+        return Ok(MasterReply::DryRun);
+    }
+    
+    if kind != MessageKind::LoadUpdate {
+        state.master_limiter.consume();
+    }
+    state.metrics.record_message(kind);
+    let result = exchange_with_master(state, message).await;
+    match &result {
+        Ok(_) => {
+            state.link.record_success();
+            if state.quarantined.swap(false, Ordering::SeqCst) {
+                info!("✅ Связь с мастером восстановлена, нода вышла из карантина");
+            }
+        }
+        Err(e) => {
+            state.metrics.record_failure(kind);
+            state.master_errors.record(unix_time(), kind, e.to_string());
+            if state.link.record_failure() {
+                warn!("🔌 Связь с мастером потеряна");
+            }
+            
+            let threshold = state.config().quarantine_after_failures;
+            if threshold > 0
+                && state.link.consecutive_failures() >= threshold
+                && !state.quarantined.swap(true, Ordering::SeqCst)
+            {
+                warn!(
+                    "🚧 Ошибок связи с мастером подряд: {}. Нода на карантине, нагрузка не обновляется до восстановления связи",
+                    threshold
+                );
+            }
+        }
+    }
+    
+    result
+}
+
+async fn exchange_with_master(state: &NodeState, message: &str) -> Result<MasterReply, Box<dyn std::error::Error>> {
+    let limit = Duration::from_millis(state.config().master_timeout_ms);
+    match tokio::time::timeout(limit, exchange(state, message)).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("обмен с мастером не завершился за {} мс", limit.as_millis()).into()),
+    }
+}
+
+async fn exchange(state: &NodeState, message: &str) -> Result<MasterReply, Box<dyn std::error::Error>> {
+    let stream = connect_with_retry(&state.config()).await?;
+    
+    let (read, mut write) = stream.into_split();
+    
+    write.write_all(message.as_bytes()).await?;
+    write.shutdown().await?;
+    
+    let limit = state.config().master_max_reply_bytes;
+    let mut reply = Vec::new();
+    read.take(limit as u64 + 1).read_to_end(&mut reply).await?;
+    if reply.is_empty() {
+        return Err("мастер закрыл соединение, не ответив".into());
+    }
+    if reply.len() > limit {
+        return Err(format!("ответ мастера превышает {} байт", limit).into());
+    }
+    
+    parse_reply(&reply)
+}
+
+async fn register_node(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let address = advertise_address(&state.config()).await?;
+    let message = RegisterMessage {
+        message_type: "register".to_string(),
+        id: state.id.clone(),
+        address: address.clone(),
+        port: state.port,
+        role: state.config().role.clone(),
+        capacity: state.config().capacity,
+        generation: state.generation,
+        token: state.config().auth_token.clone(),
+    };
+    
+    let message_json = encode(state, &message, PROTOCOL_VERSION)?;
+    let rejection = match send_to_master(state, MessageKind::Register, &message_json).await? {
+        MasterReply::DryRun => None,
+        MasterReply::Empty => Some("мастер не ответил на регистрацию".to_string()),
+        MasterReply::Response(response) if response.status != "registered" => {
+            Some(format!("мастер отклонил регистрацию: {}", response.status))
+        }
+        MasterReply::Response(response) => {
+            negotiate_version(state, response.negotiated_version);
+            None
+        }
+    };
+    
+    if let Some(reason) = rejection {
+        state.metrics.record_failure(MessageKind::Register);
+        state.master_errors.record(unix_time(), MessageKind::Register, reason.clone());
+        return Err(reason.into());
+    }
+    
+    state.registered.store(true, Ordering::SeqCst);
+    info!("✅ Нода зарегистрирована в кластере (адрес {}:{})", address, state.port);
+    Ok(())
+}
+
+fn negotiate_version(state: &NodeState, offered: Option<u32>) {
+    let version = offered.unwrap_or(1).clamp(1, PROTOCOL_VERSION);
+    if state.protocol_version.swap(version, Ordering::SeqCst) != version {
+        info!("🤝 Версия протокола с мастером: {} (нода поддерживает до {})", version, PROTOCOL_VERSION);
+    }
+}
+
+async fn deregister_node(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let message = DeregisterMessage {
+        message_type: "deregister".to_string(),
+        id: state.id.clone(),
+        token: state.config().auth_token.clone(),
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    match send_to_master(state, MessageKind::Deregister, &message_json).await? {
+        MasterReply::Response(response) if response.status != "deregistered" => {
+            Err(format!("мастер не снял ноду с регистрации: {}", response.status).into())
+        }
+        MasterReply::Empty => Err("мастер не ответил на снятие с регистрации".into()),
+        _ => {
+            state.registered.store(false, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+}
+
+async fn send_status_update(state: &NodeState, status: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let message = StatusUpdateMessage {
+        message_type: "status_update".to_string(),
+        id: state.id.clone(),
+        status: status.to_string(),
+        token: state.config().auth_token.clone(),
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    match send_to_master(state, MessageKind::StatusUpdate, &message_json).await? {
+        MasterReply::Response(response) if response.status != "updated" => {
+            Err(format!("мастер не принял статус {}: {}", status, response.status).into())
+        }
+        MasterReply::Empty => Err(format!("мастер не ответил на статус {}", status).into()),
+        _ => Ok(()),
+    }
+}
+
+async fn ping_master(state: &NodeState) -> Result<Duration, Box<dyn std::error::Error>> {
+    let message = PingMessage {
+        message_type: "ping".to_string(),
+        id: state.id.clone(),
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    let started = Instant::now();
+    match send_to_master(state, MessageKind::Ping, &message_json).await? {
+        MasterReply::Response(response) if response.status == "pong" => Ok(started.elapsed()),
+        MasterReply::Response(response) => Err(format!("мастер ответил на ping: {}", response.status).into()),
+        MasterReply::Empty => Err("мастер не ответил на ping".into()),
+        MasterReply::DryRun => Err("режим dry-run: мастер не опрашивается".into()),
+    }
+}
+
+async fn register_with_retry(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let max_attempts = state.config().register_max_attempts;
+    let mut delay = Duration::from_millis(state.config().register_backoff_ms);
+    let mut attempt = 1;
+    
+    loop {
+        match register_node(state).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < max_attempts => {
+                error!("❌ Ошибка регистрации (попытка {}/{}): {}", attempt, max_attempts, e);
+                sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(30));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn deregister_with_retry(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let max_attempts = state.config().deregister_max_attempts;
+    let mut delay = Duration::from_millis(state.config().deregister_backoff_ms);
+    let mut attempt = 1;
+    
+    loop {
+        match deregister_node(state).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < max_attempts => {
+                warn!("⚠️ Ошибка снятия с регистрации (попытка {}/{}): {}", attempt, max_attempts, e);
+                sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn send_heartbeat(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let message = HeartbeatMessage {
+        message_type: "heartbeat".to_string(),
+        id: state.id.clone(),
+        uptime: Some(get_uptime()).filter(|&uptime| uptime > 0),
+        active_connections: Some(state.active_connections.load(Ordering::SeqCst)).filter(|&active| active > 0),
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    let reply = send_to_master(state, MessageKind::Heartbeat, &message_json).await?;
+    
+    state.last_heartbeat_at.store(unix_time(), Ordering::SeqCst);
+    if let MasterReply::Response(response) = reply {
+        let limit = state.config().master_max_commands;
+        if response.commands.len() > limit {
+            warn!("⚠️ Команд мастера больше лимита ({} > {}), лишние пропущены", response.commands.len(), limit);
+        }
+        for command in response.commands.into_iter().take(limit) {
+            handle_master_command(state, &command).await;
+        }
+    }
+    Ok(())
+}
+
+async fn send_info(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let config = state.config();
+    let message = InfoMessage {
+        message_type: "info".to_string(),
+        id: state.id.clone(),
+        role: config.role.clone(),
+        capacity: config.capacity,
+        worker_version: env!("CARGO_PKG_VERSION").to_string(),
+        service_name: config.service_name.clone(),
+        metadata: config.root_metadata.0.iter().cloned().collect(),
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    match send_to_master(state, MessageKind::Info, &message_json).await? {
+        MasterReply::Response(response) if response.status != "ok" => {
+            Err(format!("мастер не принял сведения о ноде: {}", response.status).into())
+        }
+        MasterReply::Empty => Err("мастер не ответил на сведения о ноде".into()),
+        _ => Ok(()),
+    }
+}
+
+async fn handle_master_command(state: &NodeState, MasterCommand { command, token }: &MasterCommand) {
+    let config = state.config();
+    if let Some(expected) = config.auth_token.as_deref() {
+        if !token.as_deref().is_some_and(|token| auth::constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+            warn!("🔒 Команда мастера «{}» отклонена: неверный токен", command);
+            return;
+        }
+    }
+    
+    match command.as_str() {
+        "shutdown" => {
+            warn!("🛑 Получена команда мастера «shutdown»: нода останавливается");
+            state.shutdown_requested.notify_one();
+        }
+        "drain" | "undrain" => {
+            let _ = set_draining(state, command == "drain").await;
+        }
+        "config" => {
+            info!("🔁 Получена команда мастера «config»: перечитываю конфигурацию");
+            state.reload_requested.notify_one();
+        }
+        other => warn!("⚠️ Неизвестная команда мастера: {}", other),
+    }
+}
+
+async fn send_load_update(state: &NodeState) -> Result<Option<Duration>, Box<dyn std::error::Error>> {
+    let (load, load_f) = state.load_pair().await;
+    let message = LoadUpdateMessage {
+        message_type: "load_update".to_string(),
+        id: state.id.clone(),
+        load,
+        load_f,
+        load_percent: load * 100 / state.config().capacity,
+    };
+    
+    let message_json = encode(state, &message, state.protocol_version.load(Ordering::SeqCst))?;
+    match send_to_master(state, MessageKind::LoadUpdate, &message_json).await? {
+        MasterReply::Response(response) if response.status == "throttled" => {
+            let config = state.config();
+            let hint = response.retry_after_ms.unwrap_or(config.load_interval_secs * 1000);
+            Ok(Some(Duration::from_millis(hint.min(config.reconnect_backoff_max_ms))))
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn health_handler(State(state): State<NodeState>) -> Response {
+    let load = *state.load.lock().await;
+    let uptime = get_uptime();
+    let config = state.config();
+    let mut failing = Vec::new();
+    
+    let listener_error = state.listener_error.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    let http_listener = if config.self_check_interval_secs == 0 {
+        "disabled"
+    } else if let Some(error) = listener_error {
+        failing.push(FailingCheck {
+            check: "http_listener",
+            error,
+        });
+        "failed"
+    } else {
+        "ok"
+    };
+    
+    let master = if config.dry_run {
+        "disabled"
+    } else if state.link.is_connected() {
+        "ok"
+    } else {
+        let error = match state.link.since_last_success() {
+            Some(elapsed) => format!("нет связи с мастером, последний успешный обмен {} с назад", elapsed.as_secs()),
+            None => "связь с мастером ещё не установлена".to_string(),
+        };
+        failing.push(FailingCheck { check: "master", error });
+        "failed"
+    };
+    
+    let (status, code) = if http_listener == "failed" {
+        ("unhealthy", StatusCode::SERVICE_UNAVAILABLE)
+    } else if !failing.is_empty() {
+        ("degraded", StatusCode::from_u16(config.health_degraded_status).unwrap_or(StatusCode::OK))
+    } else {
+        ("healthy", StatusCode::OK)
+    };
+    
+    let response = HealthResponse {
+        status: status.to_string(),
+        node_id: state.id.clone(),
+        load,
+        uptime,
+        uptime_human: format_uptime(uptime),
+        checks: HealthChecks { http_listener, master },
+        failing,
+    };
+    (code, Json(response)).into_response()
+}
+
+fn readiness(state: &NodeState) -> &'static str {
+    if !state.registered.load(Ordering::SeqCst) {
+        "not_registered"
+    } else if !state.warmed_up.load(Ordering::SeqCst) {
+        "warming_up"
+    } else {
+        "ready"
+    }
+}
+
+async fn ready_handler(State(state): State<NodeState>) -> Response {
+    let status = readiness(&state);
+    let ready = status == "ready";
+    let response = Json(ReadyResponse { ready, status });
+    if ready {
+        response.into_response()
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, response).into_response()
+    }
+}
+
+async fn ping_handler() -> &'static str {
+    "pong"
+}
+
+async fn whoami_handler(State(state): State<NodeState>) -> String {
+    state.id.clone()
+}
+
+async fn info_handler(State(state): State<NodeState>) -> Json<InfoResponse> {
+    let load = *state.load.lock().await;
+    
+    Json(InfoResponse {
+        node_id: state.id.clone(),
+        generation: state.generation,
+        port: state.port,
+        role: state.config().role.clone(),
+        load,
+        capacity: state.config().capacity,
+        master_address: state.config().master_address.clone(),
+        protocol_version: state.protocol_version.load(Ordering::SeqCst),
+    })
+}
+
+async fn status_handler(State(state): State<NodeState>) -> Json<StatusResponse> {
+    let (load, load_f) = state.load_pair().await;
+    
+    let status = if state.quarantined.load(Ordering::SeqCst) {
+        "quarantined"
+    } else if state.draining.load(Ordering::SeqCst) {
+        "draining"
+    } else if state.over_capacity.load(Ordering::SeqCst) {
+        "over_capacity"
+    } else {
+        "active"
+    };
+    
+    Json(StatusResponse {
+        status: status.to_string(),
+        node_id: state.id.clone(),
+        load,
+        load_f,
+        capacity: state.config().capacity,
+        headroom: (state.config().capacity - load).max(0),
+        active_connections: state.active_connections.load(Ordering::SeqCst),
+        registered: state.registered.load(Ordering::SeqCst),
+        peak_load: peak_load(&state),
+        load_paused: state.load_paused.load(Ordering::SeqCst),
+        stream_subscribers: state.live.subscribers(),
+    })
+}
+
+fn since_last_heartbeat(state: &NodeState) -> u64 {
+    match state.last_heartbeat_at.load(Ordering::SeqCst) {
+        0 => get_uptime(),
+        at => unix_time().saturating_sub(at),
+    }
+}
+
+fn peak_load(state: &NodeState) -> i32 {
+    state.live.peak(unix_time().saturating_sub(state.config().peak_window_secs))
+}
+
+async fn root_handler(State(state): State<NodeState>, headers: HeaderMap) -> Response {
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"));
+    
+    if state.config().enable_dashboard && !wants_json {
+        let dashboard = include_str!("dashboard.html").replace("{{ROUTE_PREFIX}}", &state.config().route_prefix);
+        return Html(dashboard).into_response();
+    }
+    
+    api_root_handler(State(state)).await.into_response()
+}
+
+async fn api_root_handler(State(state): State<NodeState>) -> Json<HashMap<String, String>> {
+    let config = state.config();
+    let mut response: HashMap<String, String> = config.root_metadata.0.iter().cloned().collect();
+    response.insert("message".to_string(), "Worker node is running".to_string());
+    if !config.service_name.is_empty() {
+        response.insert("service".to_string(), config.service_name.clone());
+    }
+    response.insert("node_id".to_string(), state.id.clone());
+    response.insert("port".to_string(), state.port.to_string());
+    
+    Json(response)
+}
+
+async fn set_draining(state: &NodeState, draining: bool) -> Json<DrainResponse> {
+    let status = if draining { "draining" } else { "ready" };
+    let _transition = state.drain_transition.lock().await;
+    if state.draining.load(Ordering::SeqCst) == draining {
+        info!("🚰 Нода уже в статусе {}, мастер не уведомляется", status);
+        return Json(DrainResponse {
+            status: status.to_string(),
+            changed: false,
+            master_notified: false,
+        });
+    }
+    
+    state.draining.store(draining, Ordering::SeqCst);
+    info!("🚰 Статус ноды: {}", status);
+    let master_notified = match send_status_update(state, status).await {
+        Ok(()) => true,
+        Err(e) => {
+            error!("❌ Мастер не уведомлён о статусе {}: {}", status, e);
+            false
+        }
+    };
+    
+    Json(DrainResponse {
+        status: status.to_string(),
+        changed: true,
+        master_notified,
+    })
+}
+
+async fn drain_handler(State(state): State<NodeState>) -> Json<DrainResponse> {
+    set_draining(&state, true).await
+}
+
+async fn undrain_handler(State(state): State<NodeState>) -> Json<DrainResponse> {
+    set_draining(&state, false).await
+}
+
+async fn debug_state_handler(State(state): State<NodeState>) -> Json<DebugStateResponse> {
+    let load = *state.load.lock().await;
+    let last_heartbeat_at = state.last_heartbeat_at.load(Ordering::SeqCst);
+    
+    Json(DebugStateResponse {
+        node_id: state.id.clone(),
+        load,
+        load_source: format!("{:?}", state.load_provider.source()),
+        active_connections: state.active_connections.load(Ordering::SeqCst),
+        max_concurrent_requests: state.config().max_concurrent_requests,
+        registered: state.registered.load(Ordering::SeqCst),
+        ready: readiness(&state) == "ready",
+        draining: state.draining.load(Ordering::SeqCst),
+        quarantined: state.quarantined.load(Ordering::SeqCst),
+        last_heartbeat_at: (last_heartbeat_at != 0).then_some(last_heartbeat_at),
+        master_endpoint: format!("{}:{}", state.config().master_address, state.config().master_port),
+        master_connected: state.link.is_connected(),
+        reconnect_backoff_ms: state.link.current_backoff().as_millis(),
+        last_master_contact_secs_ago: state.link.since_last_success().map(|elapsed| elapsed.as_secs()),
+        dry_run: state.config().dry_run,
+        uptime: get_uptime(),
+        task_restarts: state.supervisor.restarts(),
+        runtime: state.config().debug_runtime_stats.then(runtime_stats),
+    })
+}
+
+fn runtime_stats() -> RuntimeStats {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    RuntimeStats {
+        alive_tasks: metrics.num_alive_tasks(),
+        worker_threads: metrics.num_workers(),
+    }
+}
+
+async fn topology_handler(State(state): State<NodeState>) -> Json<TopologyResponse> {
+    let config = state.config();
+    let master = format!("{}:{}", config.master_address, config.master_port);
+    let connected = state.link.is_connected();
+    
+    Json(TopologyResponse {
+        masters: vec![master.clone()],
+        active_master: connected.then_some(master),
+        connected,
+        last_success_at: state.link.since_last_success().map(|elapsed| unix_time().saturating_sub(elapsed.as_secs())),
+        protocol_version: state.protocol_version.load(Ordering::SeqCst),
+    })
+}
+
+async fn master_errors_handler(State(state): State<NodeState>) -> Json<MasterErrorsResponse> {
+    Json(MasterErrorsResponse {
+        limit: state.master_errors.limit(),
+        errors: state.master_errors.snapshot(),
+    })
+}
+
+async fn access_log_handler(State(state): State<NodeState>) -> Json<AccessLogResponse> {
+    Json(AccessLogResponse {
+        limit: state.access_log.limit(),
+        requests: state.access_log.snapshot(),
+    })
+}
+
+async fn stats_handler(State(state): State<NodeState>) -> Json<Rates> {
+    Json(state.metrics.rates())
+}
+
+async fn history_handler(State(state): State<NodeState>) -> Json<Vec<LoadSample>> {
+    Json(state.live.history())
+}
+
+async fn heartbeat_now_handler(State(state): State<NodeState>) -> Response {
+    let result = send_heartbeat(&state).await.map_err(|e| e.to_string());
+    match result {
+        Ok(()) => Json(HeartbeatNowResponse {
+            status: "ok".to_string(),
+            error: None,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("❌ Внеочередной heartbeat не отправлен: {}", e);
+            let response = HeartbeatNowResponse {
+                status: "error".to_string(),
+                error: Some(e),
+            };
+            (StatusCode::BAD_GATEWAY, Json(response)).into_response()
+        }
+    }
+}
+
+async fn master_ping_handler(State(state): State<NodeState>) -> Response {
+    let result = ping_master(&state).await.map_err(|e| e.to_string());
+    match result {
+        Ok(rtt) => {
+            let rtt_ms = rtt.as_micros() as f64 / 1000.0;
+            info!("🏓 Ping мастера: {:.1} мс", rtt_ms);
+            Json(MasterPingResponse {
+                ok: true,
+                rtt_ms: Some(rtt_ms),
+                error: None,
+            })
+            .into_response()
+        }
+        Err(e) => {
+            error!("❌ Ping мастера не прошёл: {}", e);
+            let response = MasterPingResponse {
+                ok: false,
+                rtt_ms: None,
+                error: Some(e),
+            };
+            (StatusCode::BAD_GATEWAY, Json(response)).into_response()
+        }
+    }
+}
+
+async fn reconnect_handler(State(state): State<NodeState>) -> Response {
+    let rejected = if state.stopping.load(Ordering::SeqCst) {
+        Some("нода останавливается")
+    } else if state.config().dry_run {
+        Some("режим dry-run: мастер не используется")
+    } else {
+        None
+    };
+    let flight = match (rejected, state.reconnecting.try_lock()) {
+        (None, Ok(flight)) => flight,
+        (reason, _) => {
+            let e = reason.unwrap_or("переподключение уже идёт");
+            warn!("⚠️ Переподключение по запросу отклонено: {}", e);
+            let response = ReconnectResponse {
+                status: "rejected".to_string(),
+                error: Some(e.to_string()),
+            };
+            return (StatusCode::CONFLICT, Json(response)).into_response();
+        }
+    };
+    
+    warn!("🔄 Переподключение к мастеру по запросу оператора");
+    if state.link.record_failure() {
+        info!("🔌 Связь с мастером сброшена");
+    }
+    let result = force_reconnect(&state).await.map_err(|e| e.to_string());
+    drop(flight);
+    
+    match result {
+        Ok(()) => Json(ReconnectResponse {
+            status: "reconnected".to_string(),
+            error: None,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("❌ Переподключение по запросу не удалось: {}", e);
+            let response = ReconnectResponse {
+                status: "failed".to_string(),
+                error: Some(e),
+            };
+            (StatusCode::BAD_GATEWAY, Json(response)).into_response()
+        }
+    }
+}
+
+async fn set_load_handler(State(state): State<NodeState>, ValidJson(request): ValidJson<SetLoadRequest>) -> Response {
+    let capacity = state.config().capacity;
+    let rejected = if state.load_provider.source() != LoadSource::External {
+        Some((StatusCode::CONFLICT, "нагрузка задаётся извне только при LOAD_SOURCE=external".to_string()))
+    } else if !(0.0..=capacity as f32).contains(&request.load) {
+        Some((StatusCode::BAD_REQUEST, format!("нагрузка {} вне диапазона 0..={}", request.load, capacity)))
+    } else {
+        None
+    };
+    
+    if let Some((code, e)) = rejected {
+        warn!("⚠️ Нагрузка не принята: {}", e);
+        let response = SetLoadResponse {
+            status: "error".to_string(),
+            load: None,
+            error: Some(e),
+        };
+        return (code, Json(response)).into_response();
+    }
+    
+    state.load_provider.set_external(request.load);
+    info!("📥 Внешняя нагрузка задана: {}", request.load);
+    Json(SetLoadResponse {
+        status: "ok".to_string(),
+        load: Some(request.load),
+        error: None,
+    })
+    .into_response()
+}
+
+async fn set_load_paused(state: &NodeState, paused: bool) -> Json<LoadPauseResponse> {
+    let changed = state.load_paused.swap(paused, Ordering::SeqCst) != paused;
+    let load = *state.load.lock().await;
+    if changed && paused {
+        info!("⏸️ Замер нагрузки приостановлен, удерживается значение {}", load);
+    } else if changed {
+        info!("▶️ Замер нагрузки возобновлён");
+    }
+    
+    Json(LoadPauseResponse { paused, changed, load })
+}
+
+async fn pause_load_handler(State(state): State<NodeState>) -> Json<LoadPauseResponse> {
+    set_load_paused(&state, true).await
+}
+
+async fn resume_load_handler(State(state): State<NodeState>) -> Json<LoadPauseResponse> {
+    set_load_paused(&state, false).await
+}
+
+async fn restart_tasks_handler(State(state): State<NodeState>) -> Json<TaskRestarts> {
+    Json(state.supervisor.restart().await)
+}
+
+async fn live_handler(State(state): State<NodeState>) -> Response {
+    let limit = state.config().max_stream_subscribers;
+    match state.live.subscribe(limit) {
+        Ok(receiver) => Sse::new(live::events(receiver)).keep_alive(KeepAlive::default()).into_response(),
+        Err(SubscribeError::Closed) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Err(SubscribeError::Full) => {
+            let reason = format!("достигнут лимит подписчиков MAX_STREAM_SUBSCRIBERS={}", limit);
+            warn!("⚠️ Подписка на /api/live отклонена: {}", reason);
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(serde_json::json!({ "error": "too_many_subscribers", "reason": reason })),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn validate_config_handler(
+    State(state): State<NodeState>,
+    ValidJson(changes): ValidJson<HashMap<String, serde_json::Value>>,
+) -> Json<ConfigValidationResponse> {
+    let overrides = changes
+        .into_iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(value) => (name, value),
+            value => (name, value.to_string()),
+        })
+        .collect();
+    
+    let errors = NodeConfig::check_overrides(&state.args, overrides);
+    Json(ConfigValidationResponse {
+        valid: errors.is_empty(),
+        errors,
+    })
+}
+
+async fn metrics_handler(State(state): State<NodeState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(state.link.is_connected(), peak_load(&state), since_last_heartbeat(&state)),
+    )
+}
+
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn track_connections(State(state): State<NodeState>, request: Request, next: Next) -> Response {
+    state.active_connections.fetch_add(1, Ordering::SeqCst);
+    state.metrics.record_request();
+    let _guard = ConnectionGuard(state.active_connections.clone());
+    
+    next.run(request).await
+}
+
+fn forwarded_for(request: &Request) -> Option<IpAddr> {
+    let first = request
+        .headers()
+        .get("x-forwarded-for")?
+        .to_str()
+        .ok()?
+        .split(',')
+        .next()?;
+    first.trim().parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
+fn request_span(request: &Request) -> tracing::Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.as_str())
+        .unwrap_or_default();
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| peer.ip().to_string())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+        client = %client,
+        forwarded_for = tracing::field::Empty,
+    );
+    if let Some(ip) = forwarded_for(request) {
+        span.record("forwarded_for", tracing::field::display(ip));
+    }
+    
+    span
+}
+
+fn log_response(config: &NodeConfig, response: &Response, latency: Duration) {
+    let status = response.status().as_u16();
+    let latency_ms = latency.as_millis() as u64;
+    if config.slow_request_ms > 0 && latency_ms >= config.slow_request_ms {
+        warn!(status, latency_ms, "🐢 Медленный запрос: {} мс (порог {} мс)", latency_ms, config.slow_request_ms);
+    } else {
+        debug!(status, latency_ms, "Запрос обработан");
+    }
+}
+
+fn ticker(start: Instant, period: Duration) -> Interval {
+    let mut interval = interval_at(start, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+fn retune(interval: &mut Interval, secs: u64) {
+    let period = Duration::from_secs(secs);
+    if interval.period() != period {
+        *interval = ticker(Instant::now() + period, period);
+    }
+}
+
+async fn simulate_load(state: &NodeState) {
+    let mut interval = ticker(Instant::now(), Duration::from_secs(state.config().load_interval_secs));
+    let mut first_sample = true;
+    let mut last_report: Option<Instant> = None;
+    let mut throttled_until: Option<Instant> = None;
+    
+    loop {
+        let tick = interval.tick().await;
+        retune(&mut interval, state.config().load_interval_secs);
+        if state.quarantined.load(Ordering::SeqCst) {
+            continue;
+        }
+        
+        if state.load_paused.load(Ordering::SeqCst) {
+            debug!("Замер нагрузки на паузе, мастеру уходит удерживаемое значение");
+        } else {
+            measure_load(state, &mut first_sample).await;
+        }
+        
+        let config = state.config();
+        let report_every = Duration::from_secs(config.load_report_interval_secs.min(config.heartbeat_interval_secs));
+        if last_report.is_some_and(|at| tick.duration_since(at) < report_every) {
+            debug!("Обновление нагрузки не отправлено: не прошло LOAD_REPORT_INTERVAL_SECS с прошлой отправки");
+            continue;
+        }
+        if throttled_until.is_some_and(|until| tick < until) {
+            debug!("Обновление нагрузки не отправлено: мастер просил подождать");
+            continue;
+        }
+        last_report = Some(tick);
+        
+        if state.master_limiter.try_acquire().is_err() {
+            state.metrics.record_coalesced_load_update();
+            debug!("Обновление нагрузки отложено: превышен MASTER_MAX_MSGS_PER_SEC, мастер получит следующее значение");
+            continue;
+        }
+        
+        match send_load_update(state).await {
+            Ok(Some(delay)) => {
+                warn!("🐌 Мастер притормозил обновления нагрузки, следующее через {} мс", delay.as_millis());
+                throttled_until = Some(Instant::now() + delay);
+            }
+            Ok(None) => throttled_until = None,
+            Err(e) => error!("❌ Ошибка отправки обновления нагрузки: {}", e),
+        }
+    }
+}
+
+async fn measure_load(state: &NodeState, first_sample: &mut bool) {
+    let capacity = state.config().capacity;
+    let raw = state.load_provider.sample(capacity);
+    state.over_capacity.store(raw > capacity as f32, Ordering::SeqCst);
+    if *first_sample && raw > capacity as f32 {
+        warn!(
+            "⚠️ Нагрузка при старте ({}) выше ёмкости CAPACITY={}: значения ограничены ёмкостью, проверьте CAPACITY",
+            raw, capacity
+        );
+    }
+    *first_sample = false;
+    
+    let load_f = load::clamp_to_capacity(raw, capacity, state.config().load_f_decimals);
+    let new_load = load_f.round() as i32;
+    state.set_load_pair(new_load, load_f).await;
+    state.metrics.record_load(new_load);
+    state.live.publish(LoadSample {
+        timestamp: unix_time(),
+        load: new_load,
+        load_percent: new_load * 100 / capacity,
+    });
+    
+    info!("📊 Нагрузка обновлена: {}", new_load);
+}
+
+async fn warm_up(state: &NodeState) {
+    let warmup = Duration::from_secs(state.config().warmup_secs);
+    if !warmup.is_zero() {
+        info!("🔥 Прогрев ноды: {} с, до его окончания /api/ready отвечает 503", warmup.as_secs());
+        sleep(warmup).await;
+        info!("✅ Прогрев завершён, нода готова");
+    }
+    state.warmed_up.store(true, Ordering::SeqCst);
+}
+
+async fn self_check_loop(state: &NodeState, target: self_check::Target) {
+    let period = Duration::from_secs(state.config().self_check_interval_secs);
+    let mut interval = ticker(Instant::now() + period, period);
+    let path = format!("{}/api/ping", state.config().route_prefix);
+    
+    loop {
+        interval.tick().await;
+        match self_check::probe(&target, &path).await {
+            Ok(()) => {
+                let previous = state.listener_error.lock().unwrap_or_else(PoisonError::into_inner).take();
+                if previous.is_some() {
+                    info!("✅ HTTP-listener снова отвечает на {}", path);
+                }
+            }
+            Err(e) => {
+                let previous = state.listener_error.lock().unwrap_or_else(PoisonError::into_inner).replace(e.clone());
+                if previous.is_none() {
+                    error!("❌ HTTP-listener не отвечает на {}: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+async fn heartbeat_loop(state: &NodeState) {
+    let config = state.config();
+    let start = Instant::now() + random_jitter(config.startup_jitter_ms);
+    let mut interval = ticker(start, Duration::from_secs(config.heartbeat_interval_secs));
+    
+    loop {
+        interval.tick().await;
+        retune(&mut interval, state.config().heartbeat_interval_secs);
+        
+        let failed = match send_heartbeat(state).await {
+            Ok(()) => false,
+            Err(e) => {
+                error!("❌ Ошибка отправки heartbeat: {}", e);
+                true
+            }
+        };
+        
+        if failed {
+            reconnect(state).await;
+        }
+    }
+}
+
+async fn info_loop(state: &NodeState) {
+    let mut interval = ticker(Instant::now(), Duration::from_secs(state.config().info_interval_secs));
+    
+    loop {
+        interval.tick().await;
+        retune(&mut interval, state.config().info_interval_secs);
+        
+        if !state.link.is_connected() {
+            continue;
+        }
+        if let Err(e) = send_info(state).await {
+            warn!("⚠️ Сведения о ноде не отправлены мастеру: {}", e);
+        }
+    }
+}
+
+async fn watchdog_loop(state: &NodeState, period: Duration) {
+    let mut interval = ticker(Instant::now(), period);
+    
+    loop {
+        interval.tick().await;
+        if state.listener_error.lock().unwrap_or_else(PoisonError::into_inner).is_some() {
+            debug!("HTTP-listener не отвечает, WATCHDOG=1 не отправлен");
+            continue;
+        }
+        systemd::notify("WATCHDOG=1");
+    }
+}
+
+async fn reconnect(state: &NodeState) {
+    let _flight = match state.reconnecting.try_lock() {
+        Ok(flight) => flight,
+        Err(_) => {
+            debug!("Переподключение уже идёт, жду его результата");
+            let _ = state.reconnecting.lock().await;
+            return;
+        }
+    };
+    
+    while !state.stopping.load(Ordering::SeqCst) {
+        let delay = jittered(state.link.next_backoff(), state.config().reconnect_jitter);
+        warn!("🔄 Переподключение к мастеру через {} мс", delay.as_millis());
+        sleep(delay).await;
+        
+        if let Err(e) = register_node(state).await {
+            error!("❌ Переподключение не удалось: {}", e);
+            continue;
+        }
+        
+        reconnected(state).await;
+        return;
+    }
+}
+
+async fn reconnected(state: &NodeState) {
+    info!("✅ Связь с мастером восстановлена");
+    state.metrics.record_reconnect();
+    if let Err(e) = send_info(state).await {
+        warn!("⚠️ Сведения о ноде не отправлены мастеру: {}", e);
+    }
+    let _transition = state.drain_transition.lock().await;
+    if state.draining.load(Ordering::SeqCst) {
+        if let Err(e) = send_status_update(state, "draining").await {
+            error!("❌ Мастер не уведомлён о статусе draining: {}", e);
+        }
+    }
+}
+
+async fn force_reconnect(state: &NodeState) -> Result<(), Box<dyn std::error::Error>> {
+    let config = state.config();
+    let limit = Duration::from_millis(config.master_timeout_ms);
+    match tokio::time::timeout(limit, connect_to_master(&config)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => return Err(format!("мастер недоступен: {}", e).into()),
+        Err(_) => return Err(format!("мастер не принял соединение за {} мс", limit.as_millis()).into()),
+    }
+    
+    register_node(state).await?;
+    reconnected(state).await;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn reload_on_sighup(state: &NodeState, level: &telemetry::LevelHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(e) => {
+            error!("❌ Не удалось подписаться на SIGHUP: {}", e);
+            return;
+        }
+    };
+    
+    loop {
+        tokio::select! {
+            received = hangup.recv() => {
+                if received.is_none() {
+                    return;
+                }
+                info!("🔁 SIGHUP: перечитываю конфигурацию");
+            }
+            _ = state.reload_requested.notified() => {}
+        }
+        
+        let fresh = match NodeConfig::load(&state.args) {
+            Ok(fresh) => fresh,
+            Err(e) => {
+                error!("❌ Конфигурация не перечитана, остаются старые значения: {}", e);
+                continue;
+            }
+        };
+        
+        let (next, changes) = state.config().reloaded(fresh);
+        telemetry::set_level(level, next.log_level);
+        state.config.replace(next);
+        
+        if changes.is_empty() {
+            info!("🔁 Изменяемые настройки не поменялись");
+        }
+        for change in changes {
+            info!("🔁 {}", change);
+        }
+    }
+}
+
+async fn bind_listener(config: &NodeConfig, port: u16) -> std::io::Result<server::Listener> {
+    #[cfg(unix)]
+    if let Some(path) = &config.listen_uds {
+        let socket = server::UnixSocket::bind(path)?;
+        info!("🌐 HTTP сервер запущен на unix:{}", path.display());
+        return Ok(server::Listener::Unix(socket));
+    }
+    
+    let addr = SocketAddr::new(config.bind_address, port);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    info!("🌐 HTTP сервер запущен на {}", addr);
+    Ok(server::Listener::Tcp(listener))
+}
+
+async fn bind_with_retry(config: &NodeConfig) -> std::io::Result<(server::Listener, u16)> {
+    let mut port = config.port;
+    loop {
+        match bind_listener(config, port).await {
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse
+                && config.listen_uds.is_none()
+                && port < config.port + config.port_retry =>
+            {
+                warn!("⚠️ Порт {} занят, пробую {}", port, port + 1);
+                port += 1;
+            }
+            Err(e) => return Err(e),
+            Ok(listener) => {
+                if port != config.port {
+                    info!("🔀 Порт {} занят, выбран порт {} (PORT_RETRY={})", config.port, port, config.port_retry);
+                }
+                return Ok((listener, port));
+            }
+        }
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = tokio::signal::ctrl_c();
+    
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = ctrl_c => {},
+                    _ = terminate.recv() => {},
+                }
+            }
+            Err(e) => {
+                error!("❌ Не удалось подписаться на SIGTERM: {}", e);
+                let _ = ctrl_c.await;
+            }
+        }
+    }
+    
+    #[cfg(not(unix))]
+    let _ = ctrl_c.await;
+}
+
+async fn shutdown(
+    state: &NodeState,
+    stop: watch::Sender<bool>,
+    server: JoinHandle<server::Connections>,
+    tasks: Vec<JoinHandle<()>>,
+) {
+    let mut sequence = shutdown::Sequence::new(Duration::from_secs(state.config().shutdown_grace_secs));
+    state.stopping.store(true, Ordering::SeqCst);
+    systemd::notify("STOPPING=1");
+    state.live.close();
+    
+    let connections = sequence
+        .run(async {
+            let _ = stop.send(true);
+            server.await.ok()
+        })
+        .await
+        .flatten();
+    
+    let drain_timeout = state.config().drain_timeout_secs.map_or(Duration::MAX, Duration::from_secs);
+    let drained = sequence
+        .run_capped(drain_timeout, async {
+            if let Some(connections) = connections {
+                connections.drained().await;
+            }
+        })
+        .await;
+    if drained.is_none() {
+        warn!(
+            "⚠️ Дренаж прерван, брошено незавершённых запросов: {}",
+            state.active_connections.load(Ordering::SeqCst)
+        );
+    }
+    
+    sequence
+        .run(async {
+            if let Err(e) = send_load_update(state).await {
+                error!("❌ Финальное обновление нагрузки не отправлено: {}", e);
+            }
+        })
+        .await;
+    
+    sequence
+        .run(async {
+            if let Err(e) = deregister_with_retry(state).await {
+                error!("❌ Нода не снята с регистрации: {}", e);
+            }
+        })
+        .await;
+    
+    sequence
+        .run(async {
+            for task in tasks {
+                task.abort();
+                let _ = task.await;
+            }
+        })
+        .await;
+    
+    sequence
+        .run(async {
+            state.registered.store(false, Ordering::SeqCst);
+            if state.link.record_failure() {
+                info!("🔌 Связь с мастером закрыта");
+            }
+        })
+        .await;
+    
+    let lifetime = state.metrics.lifetime();
+    info!(
+        "📈 Итоги работы: heartbeat отправлено {}, ошибок отправки {}, пиковая нагрузка {}, запросов обслужено {}, аптайм {} с",
+        lifetime.heartbeats,
+        lifetime.failures,
+        lifetime.peak_load,
+        lifetime.requests,
+        get_uptime()
+    );
+    sequence.finish();
+}
+
+fn validate_config(args: &[String]) -> i32 {
+    match NodeConfig::load_with_origins(args) {
+        Ok((config, origins)) => {
+            for origin in origins {
+                println!("⚙️ {}={} ({})", origin.name, origin.value, origin.layer);
+            }
+            println!("{:#?}", config.redacted());
+            println!("✅ Конфигурация корректна");
+            0
+        }
+        Err(e) => {
+            eprintln!("❌ Ошибка конфигурации: {}", e);
+            1
+        }
+    }
+}
+
+/// Запускает ноду и возвращается после её остановки по сигналу.
+///
+/// Глобальный tracing-подписчик ставится по возможности: если он уже установлен
+/// (например, другим тестом в том же процессе), нода пишет логи через него, а
+/// `LOG_LEVEL` и его перечитывание по `SIGHUP` не действуют.
+///
+/// `args` — аргументы командной строки без имени программы; бинарник `worker`
+/// передаёт `std::env::args().skip(1)`, встраивающая программа — свои или пустой список.
+pub async fn run(args: Vec<String>) {
+    run_node(args, None).await;
+}
+
+#[cfg(feature = "extensions")]
+pub async fn run_with_routes(args: Vec<String>, routes: Router) {
+    run_node(args, Some((EXTENSION_PATH, routes))).await;
+}
+
+async fn run_node(args: Vec<String>, extension: Option<(&str, Router)>) {
+    if config::validate_requested(&args) {
+        std::process::exit(validate_config(&args));
+    }
+    
+    let telemetry = telemetry::init();
+    
+    STARTED.get_or_init(std::time::Instant::now);
+    
+    info!("🚀 Запуск рабочей ноды...");
+    
+    let config = match NodeConfig::load_with_origins(&args) {
+        Ok((config, origins)) => {
+            for origin in origins {
+                info!("⚙️ {}={} ({})", origin.name, origin.value, origin.layer);
+            }
+            config
+        }
+        Err(e) => {
+            error!("❌ Ошибка конфигурации: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
+    let level = telemetry.level_handle();
+    telemetry::set_level(&level, config.log_level);
+    
+    let node_id = node_id::generate_id(&config, |name| std::env::var(name).ok());
+    let (listener, port) = match bind_with_retry(&config).await {
+        Ok(bound) => bound,
+        Err(e) => {
+            error!("❌ Не удалось открыть HTTP-listener: {}", e);
+            if e.kind() == std::io::ErrorKind::AddrInUse {
+                let last = config.port + config.port_retry;
+                let ports = if last == config.port { config.port.to_string() } else { format!("{}–{}", config.port, last) };
+                error!(
+                    "💡 Порт {} на {} уже занят: возможно, его использует другой воркер на этом хосте. Задайте другой PORT, разрешите PORT_RETRY или запустите ноду в отдельном контейнере либо на LISTEN_UDS",
+                    ports,
+                    config.bind_address
+                );
+            }
+            std::process::exit(1);
+        }
+    };
+    
+    let state = NodeState::new(node_id.clone(), port, config, args);
+    
+    info!("📋 ID ноды: {}", node_id);
+    info!("🧬 Поколение ноды: {}", state.generation);
+    info!("🔌 Порт: {}", port);
+    info!("🏷️ Роль: {}", state.config().role);
+    if !state.config().capacity_origin.is_empty() {
+        info!(
+            "📐 Ёмкость определена автоматически: {} ({})",
+            state.config().capacity,
+            state.config().capacity_origin
+        );
+    } else {
+        info!("📐 Ёмкость: {}", state.config().capacity);
+    }
+    info!("🎯 Мастер: {}:{}", state.config().master_address, state.config().master_port);
+    info!("📈 Источник нагрузки: {:?}", state.load_provider.source());
+    if let Some(seed) = state.config().load_seed {
+        info!("🎲 Seed генератора нагрузки: {}", seed);
+    }
+    let covered_secs = state.config().history_size as u64 * state.config().load_interval_secs;
+    if covered_secs < state.config().peak_window_secs {
+        warn!(
+            "⚠️ PEAK_WINDOW_SECS={} больше, чем покрывает история нагрузки ({} с = HISTORY_SIZE × LOAD_INTERVAL_SECS): пик считается по доступной истории",
+            state.config().peak_window_secs,
+            covered_secs
+        );
+    }
+    
+    let deadline = Instant::now() + Duration::from_secs(state.config().startup_timeout_secs);
+    if state.config().dry_run {
+        info!("🧪 Режим dry-run: сообщения мастеру только логируются");
+    } else {
+        info!("⏳ Ожидание готовности мастера...");
+        match within_startup(deadline, wait_for_master(&state.config())).await {
+            Ok(MasterWait::Ready) => {}
+            Ok(MasterWait::ShutdownRequested) => {
+                warn!("🛑 Сигнал остановки во время ожидания мастера: нода завершается, не зарегистрировавшись");
+                return;
+            }
+            Err(e) => {
+                error!("❌ Мастер не готов: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    
+    let jitter = random_jitter(state.config().startup_jitter_ms);
+    if !jitter.is_zero() {
+        info!("🎲 Задержка перед регистрацией: {} мс", jitter.as_millis());
+        within_startup(deadline, sleep(jitter)).await;
+    }
+    
+    if let Err(e) = within_startup(deadline, register_with_retry(&state)).await {
+        error!("❌ Нода не зарегистрирована после {} попыток: {}", state.config().register_max_attempts, e);
+        std::process::exit(1);
+    }
+    
+    let mut tasks = Vec::new();
+    
+    let state_clone = state.clone();
+    tasks.push(tokio::spawn(async move {
+        warm_up(&state_clone).await;
+    }));
+    
+    let state_clone = state.clone();
+    tasks.push(tokio::spawn(async move {
+        let state = &state_clone;
+        state
+            .supervisor
+            .run(|| simulate_load(state), || heartbeat_loop(state))
+            .await;
+    }));
+    
+    let state_clone = state.clone();
+    tasks.push(tokio::spawn(async move {
+        info_loop(&state_clone).await;
+    }));
+    
+    #[cfg(unix)]
+    {
+        let state_clone = state.clone();
+        tasks.push(tokio::spawn(async move {
+            reload_on_sighup(&state_clone, &level).await;
+        }));
+    }
+    
+    let cors = CorsLayer::permissive();
+    let shared_config = state.config.clone();
+    let keepalive = Duration::from_secs(state.config().http_keepalive_secs);
+    let admin = Router::new()
+        .route("/api/debug/state", get(debug_state_handler))
+        .route("/api/debug/master-errors", get(master_errors_handler))
+        .route("/api/debug/requests", get(access_log_handler))
+        .route("/api/topology", get(topology_handler))
+        .route("/api/master-ping", get(master_ping_handler))
+        .route("/api/reconnect", post(reconnect_handler))
+        .route("/api/config/validate", post(validate_config_handler))
+        .route("/api/restart-tasks", post(restart_tasks_handler))
+        .route("/api/heartbeat-now", post(heartbeat_now_handler))
+        .route("/api/load", post(set_load_handler))
+        .route("/api/load/pause", post(pause_load_handler))
+        .route("/api/load/resume", post(resume_load_handler))
+        .route("/api/drain", post(drain_handler))
+        .route("/api/undrain", post(undrain_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_admin));
+    
+    let mut routes = Router::new()
+        .route("/", get(root_handler))
+        .route("/api/root", get(api_root_handler))
+        .route("/api/health", get(health_handler))
+        .route("/api/ready", get(ready_handler))
+        .route("/api/ping", get(ping_handler))
+        .route("/api/whoami", get(whoami_handler))
+        .route("/api/stats", get(stats_handler))
+        .route("/api/live", get(live_handler))
+        .merge(admin);
+    
+    let config = state.config();
+    let optional = [
+        ("/api/info", config.enable_info, get(info_handler)),
+        ("/api/status", config.enable_status, get(status_handler)),
+        ("/api/history", config.enable_history, get(history_handler)),
+        ("/metrics", config.enable_metrics, get(metrics_handler)),
+    ];
+    let (mut enabled, mut disabled) = (Vec::new(), Vec::new());
+    for (path, on, handler) in optional {
+        if on {
+            routes = routes.route(path, handler);
+            enabled.push(path);
+        } else {
+            disabled.push(path);
+        }
+    }
+    if let Some(path) = &config.probe_path {
+        let status = StatusCode::from_u16(config.probe_status).unwrap_or(StatusCode::OK);
+        let body = config.probe_body.clone();
+        routes = routes.route(path, get(move || async move { (status, body) }));
+        enabled.push(path);
+    }
+    if let Some((path, extension)) = extension {
+        routes = routes.nest_service(path, extension);
+        info!("🧩 Подключены пользовательские маршруты: {}/...", path);
+    }
+    info!("🧭 Включены эндпоинты: {}", enabled.join(", "));
+    if !disabled.is_empty() {
+        info!("🧭 Отключены эндпоинты (отвечают 404): {}", disabled.join(", "));
+    }
+    let prefix = state.config().route_prefix.clone();
+    let routes = if prefix.is_empty() { routes } else { Router::new().nest(&prefix, routes) };
+    
+    let app = routes
+        .layer(middleware::from_fn_with_state(state.clone(), track_connections))
+        .layer(GlobalConcurrencyLimitLayer::new(state.config().max_concurrent_requests))
+        .layer(middleware::from_fn_with_state(state.clone(), shed::shed_load))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit::limit_rate))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_span)
+                .on_response(move |response: &Response, latency: Duration, _: &tracing::Span| {
+                    log_response(&shared_config.get(), response, latency)
+                }),
+        )
+        .layer(middleware::from_fn_with_state(state.clone(), access_log::record_access))
+        .layer(middleware::from_fn_with_state(state.clone(), request_id::propagate_request_id))
+        .layer(cors)
+        .with_state(state.clone());
+    
+    let (stop, stopped) = watch::channel(false);
+    let server = tokio::spawn(server::serve(listener, app, keepalive, stopped));
+    
+    systemd::warn_if_unsupported();
+    systemd::notify("READY=1");
+    if let Some(period) = systemd::watchdog_interval().filter(|_| systemd::enabled()) {
+        info!("🐕 Watchdog systemd: WATCHDOG=1 каждые {} мс", period.as_millis());
+        let state_clone = state.clone();
+        tasks.push(tokio::spawn(async move {
+            watchdog_loop(&state_clone, period).await;
+        }));
+    }
+    
+    if state.config().self_check_interval_secs > 0 {
+        let state_clone = state.clone();
+        let target = self_check::Target::new(&state.config(), port);
+        tasks.push(tokio::spawn(async move {
+            self_check_loop(&state_clone, target).await;
+        }));
+    }
+    
+    let restart_after = (state.config().max_uptime_secs > 0)
+        .then(|| jittered(Duration::from_secs(state.config().max_uptime_secs), MAX_UPTIME_JITTER));
+    if let Some(after) = restart_after {
+        info!(
+            "⏰ Плановый перезапуск через {} с (unix-время {})",
+            after.as_secs(),
+            unix_time() + after.as_secs()
+        );
+    }
+    let planned_restart = async {
+        match restart_after {
+            Some(after) => sleep(after).await,
+            None => std::future::pending().await,
+        }
+    };
+    
+    let restart = tokio::select! {
+        _ = shutdown_signal() => false,
+        _ = state.shutdown_requested.notified() => false,
+        _ = planned_restart => {
+            warn!("♻️ Истёк MAX_UPTIME_SECS: нода останавливается для перезапуска");
+            true
+        }
+    };
+    shutdown(&state, stop, server, tasks).await;
+    if restart {
+        std::process::exit(RESTART_EXIT_CODE);
+    }
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn test_state(args: &[&str]) -> NodeState {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let config = NodeConfig::load(&args).unwrap();
+        NodeState::new("test-node".to_string(), 9000, config, args)
+    }
+    
+    async fn mock_master(reply: &'static [u8]) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            stream.read_to_end(&mut request).await.unwrap();
+            stream.write_all(reply).await.unwrap();
+        });
+        port
+    }
+    
+    struct CountingMaster {
+        port: u16,
+        received: Arc<std::sync::Mutex<Vec<serde_json::Value>>>,
+    }
+    
+    impl CountingMaster {
+        async fn start(reply: fn(&serde_json::Value, usize) -> &'static str) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let received = Arc::new(std::sync::Mutex::new(Vec::<serde_json::Value>::new()));
+            let log = received.clone();
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let mut request = Vec::new();
+                    stream.read_to_end(&mut request).await.unwrap();
+                    let message: serde_json::Value = serde_json::from_slice(&request).unwrap();
+                    let seen = {
+                        let mut log = log.lock().unwrap();
+                        let seen = log.iter().filter(|earlier| earlier["type"] == message["type"]).count();
+                        log.push(message.clone());
+                        seen
+                    };
+                    stream.write_all(reply(&message, seen).as_bytes()).await.unwrap();
+                }
+            });
+            CountingMaster { port, received }
+        }
+        
+        fn state(&self, extra: &[&str]) -> NodeState {
+            let port = format!("--master-port={}", self.port);
+            let mut args = vec!["--master-address=127.0.0.1", port.as_str(), "--allow-loopback-advertise"];
+            args.extend(extra);
+            test_state(&args)
+        }
+        
+        fn received(&self, kind: &str) -> Vec<serde_json::Value> {
+            self.received.lock().unwrap().iter().filter(|message| message["type"] == kind).cloned().collect()
+        }
+    }
+    
+    fn accept_all(message: &serde_json::Value, _: usize) -> &'static str {
+        match message["type"].as_str() {
+            Some("register") => r#"{"status":"registered"}"#,
+            Some("deregister") => r#"{"status":"deregistered"}"#,
+            Some("status_update") => r#"{"status":"updated"}"#,
+            _ => r#"{"status":"ok"}"#,
+        }
+    }
+    
+    #[tokio::test]
+    async fn concurrent_reconnects_register_once() {
+        let master = CountingMaster::start(accept_all).await;
+        let state = master.state(&["--reconnect-backoff-ms=20", "--reconnect-jitter=0"]);
+        
+        let callers: Vec<_> = (0..5)
+            .map(|_| {
+                let state = state.clone();
+                tokio::spawn(async move { reconnect(&state).await })
+            })
+            .collect();
+        for caller in callers {
+            caller.await.unwrap();
+        }
+        
+        assert_eq!(master.received("register").len(), 1);
+        assert!(state.registered.load(Ordering::SeqCst));
+    }
+    
+    fn spawn_load_loop(state: &NodeState) -> JoinHandle<()> {
+        let state = state.clone();
+        tokio::spawn(async move { simulate_load(&state).await })
+    }
+    
+    #[tokio::test]
+    async fn throttled_reply_delays_next_load_update() {
+        let master = CountingMaster::start(|_, _| r#"{"status":"throttled","retry_after_ms":1500}"#).await;
+        let state = master.state(&["--load-interval-secs=1"]);
+        let load_loop = spawn_load_loop(&state);
+        
+        sleep(Duration::from_millis(1300)).await;
+        assert_eq!(master.received("load_update").len(), 1, "тик через 1 с должен быть пропущен");
+        sleep(Duration::from_millis(1200)).await;
+        assert_eq!(master.received("load_update").len(), 2);
+        load_loop.abort();
+    }
+    
+    #[tokio::test]
+    async fn load_changes_within_report_interval_send_one_update() {
+        let master = CountingMaster::start(accept_all).await;
+        let state = master.state(&["--load-interval-secs=1", "--load-report-interval-secs=2"]);
+        let load_loop = spawn_load_loop(&state);
+        
+        sleep(Duration::from_millis(1500)).await;
+        load_loop.abort();
+        assert_eq!(state.live.history().len(), 2, "нагрузка должна быть замерена дважды");
+        assert_eq!(master.received("load_update").len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn load_burst_over_master_limit_sends_last_value() {
+        let master = CountingMaster::start(accept_all).await;
+        let state = master.state(&["--load-interval-secs=1", "--master-max-msgs-per-sec=1"]);
+        state.load_paused.store(true, Ordering::SeqCst);
+        state.master_limiter.try_acquire().unwrap();
+        
+        state.set_load_pair(10, 10.0).await;
+        let load_loop = spawn_load_loop(&state);
+        for load in 11..=20 {
+            sleep(Duration::from_millis(50)).await;
+            state.set_load_pair(load, load as f32).await;
+        }
+        
+        sleep(Duration::from_millis(1000)).await;
+        load_loop.abort();
+        let loads: Vec<_> = master.received("load_update").iter().map(|message| message["load"].clone()).collect();
+        assert_eq!(loads, [serde_json::json!(20)]);
+    }
+    
+    #[tokio::test]
+    async fn deregister_succeeds_on_second_attempt() {
+        let master = CountingMaster::start(|_, seen| {
+            if seen == 0 {
+                r#"{"status":"error"}"#
+            } else {
+                r#"{"status":"deregistered"}"#
+            }
+        })
+        .await;
+        let state = master.state(&["--deregister-backoff-ms=10"]);
+        state.registered.store(true, Ordering::SeqCst);
+        
+        deregister_with_retry(&state).await.unwrap();
+        assert_eq!(master.received("deregister").len(), 2);
+        assert!(!state.registered.load(Ordering::SeqCst));
+    }
+    
+    async fn race_drain(state: &NodeState, targets: impl Iterator<Item = bool>) -> Vec<DrainResponse> {
+        let callers: Vec<_> = targets
+            .map(|draining| {
+                let state = state.clone();
+                tokio::spawn(async move { set_draining(&state, draining).await.0 })
+            })
+            .collect();
+        let mut responses = Vec::new();
+        for caller in callers {
+            responses.push(caller.await.unwrap());
+        }
+        responses
+    }
+    
+    #[tokio::test]
+    async fn concurrent_drains_send_one_status_per_transition() {
+        let master = CountingMaster::start(accept_all).await;
+        let state = master.state(&[]);
+        
+        let responses = race_drain(&state, (0..8).map(|_| true)).await;
+        assert_eq!(responses.iter().filter(|response| response.changed).count(), 1);
+        assert!(responses.iter().all(|response| response.status == "draining"));
+        assert!(responses.iter().all(|response| response.master_notified == response.changed));
+        assert_eq!(master.received("status_update").len(), 1);
+        
+        let responses = race_drain(&state, (0..16).map(|call| call % 2 == 0)).await;
+        let statuses: Vec<_> = master
+            .received("status_update")
+            .iter()
+            .map(|message| message["status"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(statuses.len(), 1 + responses.iter().filter(|response| response.changed).count());
+        assert!(statuses.windows(2).all(|pair| pair[0] != pair[1]), "{:?}", statuses);
+        let expected = if state.draining.load(Ordering::SeqCst) { "draining" } else { "ready" };
+        assert_eq!(statuses.last().unwrap(), expected);
+    }
+    
+    async fn exchange_error(reply: &'static [u8], extra: &[&str]) -> String {
+        let port = mock_master(reply).await;
+        let port = format!("--master-port={}", port);
+        let mut args = vec!["--master-address=127.0.0.1", port.as_str()];
+        args.extend(extra);
+        
+        let state = test_state(&args);
+        match exchange(&state, "{}").await {
+            Ok(_) => panic!("обмен должен был завершиться ошибкой"),
+            Err(e) => e.to_string(),
+        }
+    }
+    
+    #[test]
+    fn negotiated_version_is_stored_and_clamped() {
+        let state = test_state(&[]);
+        for (offered, stored) in [(Some(1), 1), (Some(2), 2), (None, 1), (Some(7), PROTOCOL_VERSION), (Some(0), 1)] {
+            negotiate_version(&state, offered);
+            assert_eq!(state.protocol_version.load(Ordering::SeqCst), stored, "{:?}", offered);
+        }
+    }
+    
+    #[test]
+    fn workers_with_same_config_pick_different_reconnect_delays() {
+        use rand::SeedableRng;
+        
+        let backoff = Duration::from_millis(1000);
+        let mut first = rand::rngs::StdRng::seed_from_u64(1);
+        let mut second = rand::rngs::StdRng::seed_from_u64(2);
+        let first: Vec<_> = (0..5).map(|_| jittered_with(&mut first, backoff, 0.5)).collect();
+        let second: Vec<_> = (0..5).map(|_| jittered_with(&mut second, backoff, 0.5)).collect();
+        
+        assert_ne!(first, second);
+        for delay in first.iter().chain(&second) {
+            assert!((backoff / 2..=backoff).contains(delay), "{:?}", delay);
+        }
+        assert_eq!(jittered(backoff, 0.0), backoff);
+    }
+    
+    #[test]
+    fn forwarded_for_takes_first_canonical_address() {
+        let forwarded = |value: Option<&str>| {
+            let mut request = Request::builder().uri("/");
+            if let Some(value) = value {
+                request = request.header("x-forwarded-for", value);
+            }
+            forwarded_for(&request.body(axum::body::Body::empty()).unwrap())
+        };
+        
+        assert_eq!(forwarded(Some("10.0.0.1")), Some(IpAddr::from([10, 0, 0, 1])));
+        assert_eq!(forwarded(Some(" ::ffff:10.0.0.1 , 192.168.0.1")), Some(IpAddr::from([10, 0, 0, 1])));
+        assert_eq!(forwarded(Some("2001:db8::1, 10.0.0.2")), "2001:db8::1".parse().ok());
+        assert_eq!(forwarded(Some("unknown, 10.0.0.2")), None);
+        assert_eq!(forwarded(None), None);
+    }
+    
+    #[test]
+    fn heartbeat_carries_uptime_and_connections() {
+        let message = HeartbeatMessage {
+            message_type: "heartbeat".to_string(),
+            id: "node-1".to_string(),
+            uptime: Some(42),
+            active_connections: Some(3),
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({ "type": "heartbeat", "id": "node-1", "uptime": 42, "active_connections": 3 })
+        );
+        
+        let idle = HeartbeatMessage { uptime: None, active_connections: None, ..message };
+        assert_eq!(serde_json::to_string(&idle).unwrap(), r#"{"type":"heartbeat","id":"node-1"}"#);
+    }
+    
+    #[test]
+    fn status_update_round_trips() {
+        let literal = r#"{"type":"status_update","id":"node-1","status":"draining"}"#;
+        let message = StatusUpdateMessage {
+            message_type: "status_update".to_string(),
+            id: "node-1".to_string(),
+            status: "draining".to_string(),
+            token: None,
+        };
+        assert_eq!(serde_json::to_string(&message).unwrap(), literal);
+        
+        let parsed: StatusUpdateMessage = serde_json::from_str(literal).unwrap();
+        assert_eq!(parsed.message_type, "status_update");
+        assert_eq!(parsed.status, "draining");
+        assert!(parsed.token.is_none());
+        
+        let with_token = StatusUpdateMessage { token: Some("t".to_string()), ..parsed };
+        assert_eq!(serde_json::to_value(&with_token).unwrap()["token"], "t");
+    }
+    
+    #[tokio::test]
+    async fn load_pair_survives_panic_under_lock() {
+        let state = test_state(&[]);
+        state.set_load_pair(40, 40.4).await;
+        
+        let (locked, wait_locked) = tokio::sync::oneshot::channel();
+        let (release, wait_release) = tokio::sync::oneshot::channel::<()>();
+        let holder = state.clone();
+        let panicking = tokio::spawn(async move {
+            let _load = holder.load.lock().await;
+            locked.send(()).unwrap();
+            let _ = wait_release.await;
+            panic!("паника под блокировкой нагрузки");
+        });
+        
+        wait_locked.await.unwrap();
+        let reader = state.clone();
+        let read = tokio::spawn(async move { reader.load_pair().await });
+        release.send(()).unwrap();
+        
+        assert!(panicking.await.unwrap_err().is_panic());
+        let pair = tokio::time::timeout(Duration::from_secs(1), read).await.unwrap().unwrap();
+        assert_eq!(pair, (40, 40.4));
+        
+        state.set_load_pair(41, 41.1).await;
+        assert_eq!(state.load_pair().await, (41, 41.1));
+    }
+    
+    #[tokio::test]
+    async fn busy_port_is_retried_only_when_allowed() {
+        let taken = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = taken.local_addr().unwrap().port();
+        let config = |retry: u16| {
+            let args = ["--bind-address=127.0.0.1".to_string(), format!("--port={}", port), format!("--port-retry={}", retry)];
+            NodeConfig::load(&args).unwrap()
+        };
+        
+        let Err(e) = bind_with_retry(&config(0)).await else { panic!("занятый порт должен дать ошибку") };
+        assert_eq!(e.kind(), std::io::ErrorKind::AddrInUse);
+        
+        let (_listener, chosen) = bind_with_retry(&config(5)).await.unwrap();
+        assert!(chosen > port && chosen <= port + 5, "{} -> {}", port, chosen);
+    }
+    
+    #[tokio::test]
+    async fn dry_run_registers_without_resolving_master() {
+        let state = test_state(&["--dry-run", "--master-address=master.invalid"]);
+        
+        register_node(&state).await.unwrap();
+        assert!(state.registered.load(Ordering::SeqCst));
+    }
+    
+    #[tokio::test]
+    async fn registration_stores_negotiated_version() {
+        let port = mock_master(br#"{"status":"registered","negotiated_version":1}"#).await;
+        let port = format!("--master-port={}", port);
+        let state = test_state(&["--master-address=127.0.0.1", &port, "--allow-loopback-advertise"]);
+        
+        register_node(&state).await.unwrap();
+        assert_eq!(state.protocol_version.load(Ordering::SeqCst), 1);
+    }
+    
+    #[tokio::test]
+    async fn exchange_times_out_on_silent_master() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = format!("--master-port={}", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            let (_held, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await
+        });
+        
+        let state = test_state(&["--master-address=127.0.0.1", &port, "--master-timeout-ms=200"]);
+        let started = Instant::now();
+        let Err(e) = exchange_with_master(&state, "{}").await else { panic!("молчащий мастер должен дать ошибку") };
+        assert_eq!(e.to_string(), "обмен с мастером не завершился за 200 мс");
+        assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+    }
+    
+    #[tokio::test]
+    async fn exchange_fails_when_master_half_closes_without_reply() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = format!("--master-port={}", listener.local_addr().unwrap().port());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            stream.read_to_end(&mut request).await.unwrap();
+            stream.shutdown().await.unwrap();
+            std::future::pending::<()>().await
+        });
+        
+        let state = test_state(&["--master-address=127.0.0.1", &port]);
+        let Err(e) = exchange(&state, "{}").await else { panic!("пустой ответ должен дать ошибку") };
+        assert_eq!(e.to_string(), "мастер закрыл соединение, не ответив");
+    }
+    
+    #[tokio::test]
+    async fn exchange_accepts_reply_after_client_half_close() {
+        let port = mock_master(br#"{"status":"ok"}"#).await;
+        let port = format!("--master-port={}", port);
+        let state = test_state(&["--master-address=127.0.0.1", &port]);
+        
+        match exchange(&state, "{}").await {
+            Ok(MasterReply::Response(response)) => assert_eq!(response.status, "ok"),
+            Ok(_) => panic!("ожидался ответ мастера"),
+            Err(e) => panic!("обмен не удался: {}", e),
+        }
+    }
+    
+    #[tokio::test]
+    async fn connect_retries_once_after_refusal() {
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        let config = |retries: &str| {
+            let args = ["--master-address=127.0.0.1".to_string(), format!("--master-port={}", port), retries.to_string()];
+            NodeConfig::load(&args).unwrap()
+        };
+        
+        assert!(connect_with_retry(&config("--master-connect-retries=0")).await.is_err());
+        
+        tokio::spawn(async move {
+            sleep(CONNECT_RETRY_DELAY / 2).await;
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await.unwrap();
+            let _accepted = listener.accept().await.unwrap();
+        });
+        let started = Instant::now();
+        connect_with_retry(&config("--master-connect-retries=1")).await.unwrap();
+        assert!(started.elapsed() >= CONNECT_RETRY_DELAY, "{:?}", started.elapsed());
+    }
+    
+    #[tokio::test]
+    async fn exchange_rejects_oversized_reply() {
+        let error = exchange_error(&[b'x'; 32], &["--master-max-reply-bytes=16"]).await;
+        assert_eq!(error, "ответ мастера превышает 16 байт");
+    }
+    
+    #[tokio::test]
+    async fn exchange_rejects_non_utf8_reply() {
+        let error = exchange_error(&[0xff, 0xfe, 0xfd], &[]).await;
+        assert!(error.starts_with("ответ мастера не в UTF-8"), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn exchange_rejects_non_json_reply() {
+        let error = exchange_error(b"not json", &[]).await;
+        assert!(error.starts_with("ответ мастера не является корректным JSON"), "{}", error);
+    }
+}
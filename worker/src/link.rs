@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+struct LinkTimes {
+    backoff: Duration,
+    connected_since: Option<Instant>,
+    last_success: Option<Instant>,
+}
+
+pub struct MasterLink {
+    connected: AtomicBool,
+    consecutive_failures: AtomicU32,
+    times: Mutex<LinkTimes>,
+    base_backoff: Duration,
+    max_backoff: Duration,
+    reset_after: Duration,
+}
+
+impl MasterLink {
+    pub fn new(base_backoff: Duration, max_backoff: Duration, reset_after: Duration) -> Self {
+        MasterLink {
+            connected: AtomicBool::new(false),
+            consecutive_failures: AtomicU32::new(0),
+            times: Mutex::new(LinkTimes {
+                backoff: base_backoff,
+                connected_since: None,
+                last_success: None,
+            }),
+            base_backoff,
+            max_backoff,
+            reset_after,
+        }
+    }
+    
+    fn times(&self) -> std::sync::MutexGuard<'_, LinkTimes> {
+        self.times.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+    
+    pub fn record_success(&self) {
+        let now = Instant::now();
+        let mut times = self.times();
+        let connected_since = *times.connected_since.get_or_insert(now);
+        times.last_success = Some(now);
+        if now.duration_since(connected_since) >= self.reset_after {
+            times.backoff = self.base_backoff;
+        }
+        
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+        self.connected.store(true, Ordering::SeqCst);
+    }
+    
+    pub fn record_failure(&self) -> bool {
+        self.times().connected_since = None;
+        self.consecutive_failures.fetch_add(1, Ordering::SeqCst);
+        self.connected.swap(false, Ordering::SeqCst)
+    }
+    
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::SeqCst)
+    }
+    
+    pub fn next_backoff(&self) -> Duration {
+        let mut times = self.times();
+        let delay = times.backoff;
+        times.backoff = (delay * 2).min(self.max_backoff);
+        delay
+    }
+    
+    pub fn current_backoff(&self) -> Duration {
+        self.times().backoff
+    }
+    
+    pub fn since_last_success(&self) -> Option<Duration> {
+        self.times().last_success.map(|at| at.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const BASE: Duration = Duration::from_millis(10);
+    const RESET_AFTER: Duration = Duration::from_millis(50);
+    
+    #[test]
+    fn flapping_link_keeps_backoff_until_stable() {
+        let link = MasterLink::new(BASE, Duration::from_secs(1), RESET_AFTER);
+        assert!(!link.is_connected());
+        
+        link.record_success();
+        assert!(link.is_connected());
+        
+        assert!(link.record_failure(), "первая ошибка — переход в down");
+        assert!(!link.record_failure(), "повторная ошибка — уже down");
+        assert_eq!(link.consecutive_failures(), 2);
+        assert_eq!(link.next_backoff(), BASE);
+        assert_eq!(link.next_backoff(), BASE * 2);
+        
+        link.record_success();
+        assert!(link.is_connected());
+        assert_eq!(link.consecutive_failures(), 0);
+        assert_eq!(link.current_backoff(), BASE * 4, "после короткого подъёма пауза не сбрасывается");
+        
+        assert!(link.record_failure());
+        assert!(!link.is_connected());
+        
+        link.record_success();
+        std::thread::sleep(RESET_AFTER);
+        link.record_success();
+        assert!(link.is_connected());
+        assert_eq!(link.current_backoff(), BASE);
+        assert!(link.since_last_success().is_some_and(|elapsed| elapsed < RESET_AFTER));
+    }
+}
@@ -0,0 +1,165 @@
+use axum::response::sse::Event;
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::{Mutex, PoisonError};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+
+pub enum SubscribeError {
+    Closed,
+    Full,
+}
+
+#[derive(Clone, Serialize)]
+pub struct LoadSample {
+    pub timestamp: u64,
+    pub load: i32,
+    pub load_percent: i32,
+}
+
+pub struct LiveLoad {
+    sender: Mutex<Option<broadcast::Sender<LoadSample>>>,
+    history: Mutex<VecDeque<LoadSample>>,
+    history_len: usize,
+}
+
+impl LiveLoad {
+    pub fn new(capacity: usize, history_len: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        LiveLoad {
+            sender: Mutex::new(Some(sender)),
+            history: Mutex::new(VecDeque::with_capacity(history_len)),
+            history_len,
+        }
+    }
+    
+    pub fn publish(&self, sample: LoadSample) {
+        if self.history_len > 0 {
+            let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+            if history.len() == self.history_len {
+                history.pop_front();
+            }
+            history.push_back(sample.clone());
+        }
+        
+        if let Some(sender) = self.sender.lock().unwrap_or_else(PoisonError::into_inner).as_ref() {
+            let _ = sender.send(sample);
+        }
+    }
+    
+    pub fn subscribe(&self, limit: usize) -> Result<broadcast::Receiver<LoadSample>, SubscribeError> {
+        let sender = self.sender.lock().unwrap_or_else(PoisonError::into_inner);
+        let sender = sender.as_ref().ok_or(SubscribeError::Closed)?;
+        if limit > 0 && sender.receiver_count() >= limit {
+            return Err(SubscribeError::Full);
+        }
+        Ok(sender.subscribe())
+    }
+    
+    pub fn subscribers(&self) -> usize {
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map_or(0, broadcast::Sender::receiver_count)
+    }
+    
+    pub fn history(&self) -> Vec<LoadSample> {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner).iter().cloned().collect()
+    }
+    
+    pub fn peak(&self, since: u64) -> i32 {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        history
+            .iter()
+            .filter(|sample| sample.timestamp >= since)
+            .map(|sample| sample.load)
+            .max()
+            .unwrap_or(0)
+    }
+    
+    pub fn close(&self) {
+        self.sender.lock().unwrap_or_else(PoisonError::into_inner).take();
+    }
+}
+
+fn sample_event(sample: &LoadSample) -> Event {
+    Event::default()
+        .event("load")
+        .json_data(sample)
+        .unwrap_or_else(|_| Event::default().event("load"))
+}
+
+fn lagged_event(missed: u64) -> Event {
+    Event::default()
+        .event("lagged")
+        .data(serde_json::json!({ "missed": missed }).to_string())
+}
+
+pub fn events(receiver: broadcast::Receiver<LoadSample>) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold((receiver, None), |(mut receiver, pending)| async move {
+        if let Some(event) = pending {
+            return Some((Ok(event), (receiver, None)));
+        }
+        
+        match receiver.recv().await {
+            Ok(sample) => Some((Ok(sample_event(&sample)), (receiver, None))),
+            Err(RecvError::Lagged(mut missed)) => {
+                let mut latest = None;
+                while let Ok(sample) = receiver.try_recv() {
+                    if latest.replace(sample).is_some() {
+                        missed += 1;
+                    }
+                }
+                
+                debug!("Подписчик живой нагрузки отстал на {} обновлений", missed);
+                let latest = latest.as_ref().map(sample_event);
+                Some((Ok(lagged_event(missed)), (receiver, latest)))
+            }
+            Err(RecvError::Closed) => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    
+    fn sample(load: i32) -> LoadSample {
+        LoadSample { timestamp: load as u64, load, load_percent: load }
+    }
+    
+    #[tokio::test]
+    async fn slow_subscriber_gets_lag_notice_then_latest() {
+        let live = LiveLoad::new(4, 0);
+        let Ok(receiver) = live.subscribe(0) else { panic!("подписка не удалась") };
+        for load in 0..10 {
+            live.publish(sample(load));
+        }
+        live.close();
+        
+        let events: Vec<String> = events(receiver)
+            .map(|event| format!("{:?}", event.unwrap()))
+            .collect()
+            .await;
+        assert_eq!(events.len(), 2, "{:?}", events);
+        assert!(events[0].contains("event: lagged") && events[0].contains(r#"{\"missed\":9}"#), "{}", events[0]);
+        assert!(events[1].contains("event: load") && events[1].contains(r#"\"load\":9"#), "{}", events[1]);
+    }
+    
+    #[test]
+    fn subscribe_stops_at_limit_until_one_leaves() {
+        let live = LiveLoad::new(4, 0);
+        let mut receivers: Vec<_> = (0..3)
+            .map(|_| live.subscribe(3).unwrap_or_else(|_| panic!("подписка не удалась")))
+            .collect();
+        assert!(matches!(live.subscribe(3), Err(SubscribeError::Full)));
+        assert_eq!(live.subscribers(), 3);
+        
+        receivers.pop();
+        assert!(live.subscribe(3).is_ok());
+    }
+}
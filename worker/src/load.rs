@@ -0,0 +1,120 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use tracing::debug;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoadSource {
+    Simulated,
+    Connections,
+    External,
+}
+
+impl FromStr for LoadSource {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "simulated" => Ok(LoadSource::Simulated),
+            "connections" => Ok(LoadSource::Connections),
+            "external" => Ok(LoadSource::External),
+            other => Err(format!("неизвестный источник нагрузки: {}", other)),
+        }
+    }
+}
+
+pub struct LoadProvider {
+    source: LoadSource,
+    active_connections: Arc<AtomicUsize>,
+    max_concurrent_requests: usize,
+    rng: Mutex<StdRng>,
+    external: Mutex<f32>,
+}
+
+impl LoadProvider {
+    pub fn new(
+        source: LoadSource,
+        active_connections: Arc<AtomicUsize>,
+        max_concurrent_requests: usize,
+        seed: Option<u64>,
+    ) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        
+        LoadProvider {
+            source,
+            active_connections,
+            max_concurrent_requests,
+            rng: Mutex::new(rng),
+            external: Mutex::new(0.0),
+        }
+    }
+    
+    pub fn source(&self) -> LoadSource {
+        self.source
+    }
+    
+    pub fn set_external(&self, load: f32) {
+        *self.external.lock().unwrap_or_else(PoisonError::into_inner) = load;
+    }
+    
+    pub fn sample(&self, capacity: i32) -> f32 {
+        match self.source {
+            LoadSource::Simulated => self
+                .rng
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .gen_range(0..100) as f32,
+            LoadSource::Connections => {
+                let active = self.active_connections.load(Ordering::SeqCst);
+                let capacity = capacity as f32;
+                (active as f32 * capacity / self.max_concurrent_requests as f32).min(capacity)
+            }
+            LoadSource::External => *self.external.lock().unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+}
+
+pub fn clamp_to_capacity(raw: f32, capacity: i32, decimals: u32) -> f32 {
+    let scale = 10f32.powi(decimals as i32);
+    let load = (raw.clamp(0.0, capacity as f32) * scale).round() / scale;
+    if raw < 0.0 || raw > capacity as f32 {
+        debug!("Нагрузка {} вне диапазона 0..={}, отправляется {}", raw, capacity, load);
+    }
+    
+    load
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn provider(source: LoadSource, active: usize, max_concurrent_requests: usize) -> LoadProvider {
+        LoadProvider::new(source, Arc::new(AtomicUsize::new(active)), max_concurrent_requests, Some(1))
+    }
+    
+    #[test]
+    fn connections_are_scaled_to_capacity() {
+        assert_eq!(provider(LoadSource::Connections, 20, 100).sample(20), 4.0);
+        assert_eq!(provider(LoadSource::Connections, 50, 100).sample(20), 10.0);
+        assert_eq!(provider(LoadSource::Connections, 100, 100).sample(20), 20.0);
+        assert_eq!(provider(LoadSource::Connections, 3, 4).sample(200), 150.0);
+    }
+    
+    #[test]
+    fn over_capacity_reading_is_clamped() {
+        let provider = provider(LoadSource::External, 0, 100);
+        provider.set_external(35.5);
+        assert_eq!(clamp_to_capacity(provider.sample(20), 20, 2), 20.0);
+        
+        provider.set_external(-3.0);
+        assert_eq!(clamp_to_capacity(provider.sample(20), 20, 2), 0.0);
+        
+        provider.set_external(12.345);
+        assert_eq!(clamp_to_capacity(provider.sample(20), 20, 2), 12.35);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+use crate::metrics::MessageKind;
+
+#[derive(Clone, Serialize)]
+pub struct MasterError {
+    pub timestamp: u64,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub error: String,
+}
+
+pub struct MasterErrorLog {
+    entries: Mutex<VecDeque<MasterError>>,
+    limit: usize,
+}
+
+impl MasterErrorLog {
+    pub fn new(limit: usize) -> Self {
+        MasterErrorLog {
+            entries: Mutex::new(VecDeque::new()),
+            limit,
+        }
+    }
+    
+    pub fn record(&self, timestamp: u64, kind: MessageKind, error: String) {
+        if self.limit == 0 {
+            return;
+        }
+        
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if entries.len() == self.limit {
+            entries.pop_front();
+        }
+        entries.push_back(MasterError {
+            timestamp,
+            kind: kind.as_str(),
+            error,
+        });
+    }
+    
+    pub fn snapshot(&self) -> Vec<MasterError> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .cloned()
+            .collect()
+    }
+    
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+pub const WINDOW_SECS: u64 = 60;
+
+const ENCODE_BUCKETS: [f64; 8] = [0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageKind {
+    Register,
+    Heartbeat,
+    LoadUpdate,
+    Deregister,
+    StatusUpdate,
+    Ping,
+    Info,
+}
+
+const KINDS: usize = 7;
+
+impl MessageKind {
+    const ALL: [MessageKind; KINDS] = [
+        MessageKind::Register,
+        MessageKind::Heartbeat,
+        MessageKind::LoadUpdate,
+        MessageKind::Deregister,
+        MessageKind::StatusUpdate,
+        MessageKind::Ping,
+        MessageKind::Info,
+    ];
+    
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Register => "register",
+            MessageKind::Heartbeat => "heartbeat",
+            MessageKind::LoadUpdate => "load_update",
+            MessageKind::Deregister => "deregister",
+            MessageKind::StatusUpdate => "status_update",
+            MessageKind::Ping => "ping",
+            MessageKind::Info => "info",
+        }
+    }
+    
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct RollingCounter {
+    buckets: Mutex<[(u64, u64); WINDOW_SECS as usize]>,
+}
+
+impl Default for RollingCounter {
+    fn default() -> Self {
+        RollingCounter {
+            buckets: Mutex::new([(0, 0); WINDOW_SECS as usize]),
+        }
+    }
+}
+
+impl RollingCounter {
+    fn record(&self, second: u64) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let bucket = &mut buckets[(second % WINDOW_SECS) as usize];
+        if bucket.0 != second {
+            *bucket = (second, 0);
+        }
+        bucket.1 += 1;
+    }
+    
+    fn total(&self, second: u64) -> u64 {
+        let buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        buckets
+            .iter()
+            .filter(|(at, _)| second.saturating_sub(*at) < WINDOW_SECS)
+            .map(|(_, count)| count)
+            .sum()
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [AtomicU64; ENCODE_BUCKETS.len()],
+    count: AtomicU64,
+    sum_nanos: AtomicU64,
+}
+
+impl Histogram {
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(ENCODE_BUCKETS) {
+            if secs <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_nanos.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+    
+    fn write(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bucket, bound) in self.buckets.iter().zip(ENCODE_BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, bucket.load(Ordering::Relaxed));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+#[derive(Serialize)]
+pub struct Rates {
+    pub window_secs: u64,
+    pub heartbeats_per_sec: f64,
+    pub load_updates_per_sec: f64,
+    pub requests_per_sec: f64,
+}
+
+pub struct Lifetime {
+    pub heartbeats: u64,
+    pub failures: u64,
+    pub peak_load: i32,
+    pub requests: u64,
+}
+
+pub struct Metrics {
+    started: Instant,
+    messages: [AtomicU64; KINDS],
+    failures: [AtomicU64; KINDS],
+    recent_messages: [RollingCounter; KINDS],
+    recent_requests: RollingCounter,
+    coalesced_load_updates: AtomicU64,
+    reconnects: AtomicU64,
+    peak_load: AtomicI32,
+    requests: AtomicU64,
+    encode: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics {
+            started: Instant::now(),
+            messages: Default::default(),
+            failures: Default::default(),
+            recent_messages: Default::default(),
+            recent_requests: RollingCounter::default(),
+            coalesced_load_updates: AtomicU64::new(0),
+            reconnects: AtomicU64::new(0),
+            peak_load: AtomicI32::new(0),
+            requests: AtomicU64::new(0),
+            encode: Histogram::default(),
+        }
+    }
+}
+
+impl Metrics {
+    fn second(&self) -> u64 {
+        self.started.elapsed().as_secs()
+    }
+    
+    pub fn record_message(&self, kind: MessageKind) {
+        self.messages[kind.index()].fetch_add(1, Ordering::Relaxed);
+        self.recent_messages[kind.index()].record(self.second());
+    }
+    
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.recent_requests.record(self.second());
+    }
+    
+    pub fn record_load(&self, load: i32) {
+        self.peak_load.fetch_max(load, Ordering::Relaxed);
+    }
+    
+    pub fn lifetime(&self) -> Lifetime {
+        Lifetime {
+            heartbeats: self.messages[MessageKind::Heartbeat.index()].load(Ordering::Relaxed),
+            failures: self.failures.iter().map(|count| count.load(Ordering::Relaxed)).sum(),
+            peak_load: self.peak_load.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+        }
+    }
+    
+    pub fn rates(&self) -> Rates {
+        let second = self.second();
+        let window = (second + 1).min(WINDOW_SECS);
+        let rate = |counter: &RollingCounter| counter.total(second) as f64 / window as f64;
+        
+        Rates {
+            window_secs: window,
+            heartbeats_per_sec: rate(&self.recent_messages[MessageKind::Heartbeat.index()]),
+            load_updates_per_sec: rate(&self.recent_messages[MessageKind::LoadUpdate.index()]),
+            requests_per_sec: rate(&self.recent_requests),
+        }
+    }
+    
+    pub fn record_failure(&self, kind: MessageKind) {
+        self.failures[kind.index()].fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub fn record_coalesced_load_update(&self) {
+        self.coalesced_load_updates.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub fn record_encode(&self, elapsed: Duration) {
+        self.encode.observe(elapsed);
+    }
+    
+    pub fn render(&self, connected: bool, peak_load: i32, since_heartbeat: u64) -> String {
+        let mut out = String::new();
+        
+        write_per_kind(
+            &mut out,
+            "worker_master_messages_total",
+            "Messages sent to the master, by message type.",
+            &self.messages,
+        );
+        write_per_kind(
+            &mut out,
+            "worker_master_message_failures_total",
+            "Messages to the master that failed, by message type.",
+            &self.failures,
+        );
+        
+        let name = "worker_master_load_updates_coalesced_total";
+        let _ = writeln!(out, "# HELP {} Load updates held back by MASTER_MAX_MSGS_PER_SEC.", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, self.coalesced_load_updates.load(Ordering::Relaxed));
+        
+        let name = "worker_master_reconnects_total";
+        let _ = writeln!(out, "# HELP {} Successful re-registrations after the master link was lost.", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, self.reconnects.load(Ordering::Relaxed));
+        
+        let name = "worker_master_connected";
+        let _ = writeln!(out, "# HELP {} Whether the last message to the master succeeded (1) or not (0).", name);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, u8::from(connected));
+        
+        let name = "worker_load_peak";
+        let _ = writeln!(out, "# HELP {} Highest load within the last PEAK_WINDOW_SECS.", name);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, peak_load);
+        
+        let name = "worker_seconds_since_last_heartbeat";
+        let _ = writeln!(out, "# HELP {} Seconds since the last heartbeat the master accepted (since start before the first one).", name);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, since_heartbeat);
+        
+        self.encode.write(&mut out, "worker_encode_seconds", "Time spent encoding messages to the master.");
+        
+        out
+    }
+}
+
+fn write_per_kind(out: &mut String, name: &str, help: &str, values: &[AtomicU64; KINDS]) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for kind in MessageKind::ALL {
+        let value = values[kind.index()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}{{type=\"{}\"}} {}", name, kind.as_str(), value);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::NodeConfig;
+
+pub fn generation() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+pub fn generate_id(config: &NodeConfig, env: impl Fn(&str) -> Option<String>) -> String {
+    if let Some(id) = &config.node_id {
+        info!("🪪 ID ноды задан в NODE_ID");
+        return id.clone();
+    }
+    
+    if let Some(hostname) = env("HOSTNAME").filter(|value| !value.trim().is_empty()) {
+        info!("🪪 ID ноды взят из HOSTNAME");
+        return hostname.trim().to_string();
+    }
+    
+    let Some(path) = &config.node_id_file else {
+        return Uuid::new_v4().to_string();
+    };
+    
+    match fs::read_to_string(path) {
+        Ok(contents) if !contents.trim().is_empty() => {
+            info!("🪪 ID ноды прочитан из {}", path.display());
+            return contents.trim().to_string();
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("⚠️ Не удалось прочитать {}: {}", path.display(), e),
+    }
+    
+    let id = Uuid::new_v4().to_string();
+    match fs::write(path, format!("{}\n", id)) {
+        Ok(()) => info!("🪪 Новый ID ноды сохранён в {}", path.display()),
+        Err(e) => warn!("⚠️ Не удалось сохранить ID ноды в {}: {}", path.display(), e),
+    }
+    
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    fn config(args: &[String]) -> NodeConfig {
+        NodeConfig::load(args).unwrap()
+    }
+    
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+    
+    fn hostname(name: &str) -> Option<String> {
+        (name == "HOSTNAME").then(|| "worker-host".to_string())
+    }
+    
+    fn id_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("worker-node-id-{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+    
+    #[test]
+    fn node_id_wins() {
+        let config = config(&["--node-id=fixed".to_string()]);
+        assert_eq!(generate_id(&config, hostname), "fixed");
+    }
+    
+    #[test]
+    fn hostname_is_used_without_node_id() {
+        let config = config(&[]);
+        assert_eq!(generate_id(&config, hostname), "worker-host");
+    }
+    
+    #[test]
+    fn node_id_file_is_read_then_persisted() {
+        let path = id_file("read");
+        fs::write(&path, "from-file\n").unwrap();
+        let config = config(&[format!("--node-id-file={}", path.display())]);
+        assert_eq!(generate_id(&config, no_env), "from-file");
+        
+        fs::remove_file(&path).unwrap();
+        let created = generate_id(&config, no_env);
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), created);
+        assert_eq!(generate_id(&config, no_env), created);
+        fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn falls_back_to_random_uuid() {
+        let config = config(&[]);
+        let first = generate_id(&config, no_env);
+        assert!(Uuid::parse_str(&first).is_ok(), "{}", first);
+        assert_ne!(first, generate_id(&config, no_env));
+    }
+}
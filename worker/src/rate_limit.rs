@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
+use tracing::debug;
+
+use crate::{shed, NodeState};
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32, burst: u32) -> Self {
+        RateLimiter {
+            rate: requests_per_second as f64,
+            burst: burst as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+    
+    fn refilled(&self) -> MutexGuard<'_, Bucket> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled_at = now;
+        bucket
+    }
+    
+    pub fn try_acquire(&self) -> Result<(), u64> {
+        if self.rate == 0.0 {
+            return Ok(());
+        }
+        
+        let mut bucket = self.refilled();
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        
+        let retry_after = ((1.0 - bucket.tokens) / self.rate).ceil() as u64;
+        Err(retry_after.max(1))
+    }
+    
+    pub fn consume(&self) {
+        if self.rate == 0.0 {
+            return;
+        }
+        
+        let mut bucket = self.refilled();
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+    }
+}
+
+pub async fn limit_rate(State(state): State<NodeState>, request: Request, next: Next) -> Response {
+    let config = state.config();
+    if shed::PROTECTED.contains(&shed::route_path(&config.route_prefix, request.uri().path())) {
+        return next.run(request).await;
+    }
+
+    let retry_after = match state.rate_limiter.try_acquire() {
+        Ok(()) => return next.run(request).await,
+        Err(retry_after) => retry_after,
+    };
+
+    debug!("Запрос {} отклонён лимитом частоты", request.uri().path());
+    let status = StatusCode::from_u16(config.rate_limit_status).unwrap_or(StatusCode::TOO_MANY_REQUESTS);
+    let mut response = match &config.rate_limit_body {
+        Some(body) => (status, [(header::CONTENT_TYPE, "application/json")], body.clone()).into_response(),
+        None => status.into_response(),
+    };
+    response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    #[test]
+    fn allows_burst_then_rejects() {
+        let limiter = RateLimiter::new(1, 3);
+        for _ in 0..3 {
+            assert_eq!(limiter.try_acquire(), Ok(()));
+        }
+        assert_eq!(limiter.try_acquire(), Err(1));
+    }
+    
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(100, 1);
+        assert_eq!(limiter.try_acquire(), Ok(()));
+        assert!(limiter.try_acquire().is_err());
+        
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(limiter.try_acquire(), Ok(()));
+    }
+    
+    #[test]
+    fn retry_after_is_at_least_one_second() {
+        let limiter = RateLimiter::new(1000, 1);
+        assert_eq!(limiter.try_acquire(), Ok(()));
+        assert_eq!(limiter.try_acquire(), Err(1));
+    }
+    
+    #[test]
+    fn zero_rate_disables_limit() {
+        let limiter = RateLimiter::new(0, 0);
+        for _ in 0..10 {
+            assert_eq!(limiter.try_acquire(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use uuid::Uuid;
+
+use crate::NodeState;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+fn is_valid(id: &str, max_len: usize) -> bool {
+    !id.is_empty()
+        && id.len() <= max_len
+        && id.bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+}
+
+pub async fn propagate_request_id(State(state): State<NodeState>, mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id, state.config().request_id_max_len))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
+    
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn accepts_only_short_ascii_ids() {
+        let cases = [
+            ("abc-123", 64, true),
+            ("550e8400-e29b-41d4-a716-446655440000", 64, true),
+            ("12345678", 8, true),
+            ("123456789", 8, false),
+            ("", 64, false),
+            ("id with space", 64, false),
+            ("id_1", 64, false),
+            ("идентификатор", 64, false),
+            ("naïve", 64, false),
+        ];
+        for (id, max_len, valid) in cases {
+            assert_eq!(is_valid(id, max_len), valid, "{:?}", id);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(unix)]
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+use crate::config::NodeConfig;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub enum Target {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Target {
+    pub fn new(config: &NodeConfig, port: u16) -> Self {
+        #[cfg(unix)]
+        if let Some(path) = &config.listen_uds {
+            return Target::Unix(path.clone());
+        }
+        
+        let ip = match config.bind_address {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        Target::Tcp(SocketAddr::new(ip, port))
+    }
+}
+
+async fn request<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, path: &str) -> Result<(), String> {
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.map_err(|e| e.to_string())?;
+    
+    let mut head = [0u8; 12];
+    stream.read_exact(&mut head).await.map_err(|e| e.to_string())?;
+    match head.split_at(9) {
+        (b"HTTP/1.1 ", status) if status.iter().all(u8::is_ascii_digit) => Ok(()),
+        _ => Err(format!("неожиданный ответ: {}", String::from_utf8_lossy(&head))),
+    }
+}
+
+pub async fn probe(target: &Target, path: &str) -> Result<(), String> {
+    let attempt = async {
+        match target {
+            Target::Tcp(addr) => request(TcpStream::connect(addr).await.map_err(|e| e.to_string())?, path).await,
+            #[cfg(unix)]
+            Target::Unix(socket) => {
+                let stream = tokio::net::UnixStream::connect(socket).await.map_err(|e| e.to_string())?;
+                request(stream, path).await
+            }
+        }
+    };
+    
+    timeout(PROBE_TIMEOUT, attempt)
+        .await
+        .unwrap_or_else(|_| Err(format!("нет ответа за {} с", PROBE_TIMEOUT.as_secs())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    async fn answer(reply: &'static [u8]) -> Result<(), String> {
+        let (client, mut server) = tokio::io::duplex(1024);
+        tokio::spawn(async move {
+            let mut request = [0u8; 16];
+            server.read_exact(&mut request).await.unwrap();
+            server.write_all(reply).await.unwrap();
+        });
+        request(client, "/api/health").await
+    }
+    
+    #[tokio::test]
+    async fn any_status_line_means_serving() {
+        for reply in [
+            &b"HTTP/1.1 200 OK\r\n"[..],
+            b"HTTP/1.1 429 Too Many Requests\r\n",
+            b"HTTP/1.1 503 Service Unavailable\r\n",
+        ] {
+            assert_eq!(answer(reply).await, Ok(()));
+        }
+    }
+    
+    #[tokio::test]
+    async fn malformed_status_line_fails() {
+        assert!(answer(b"SSH-2.0-OpenSSH_9.6\r\n").await.is_err());
+        assert!(answer(b"HTTP/1.1 2xx OK\r\n").await.is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use axum::extract::{ConnectInfo, Request};
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::{TokioIo, TokioTimer};
+use hyper_util::service::TowerToHyperService;
+use std::net::SocketAddr;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Duration;
+use tower::ServiceExt;
+use tracing::{debug, error};
+
+#[cfg(unix)]
+pub use unix::UnixSocket;
+
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixSocket),
+}
+
+enum Accepted {
+    Tcp(TcpStream, SocketAddr),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+}
+
+impl Listener {
+    async fn accept(&self) -> std::io::Result<Accepted> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().await.map(|(stream, peer)| Accepted::Tcp(stream, peer)),
+            #[cfg(unix)]
+            Listener::Unix(socket) => socket.listener.accept().await.map(|(stream, _)| Accepted::Unix(stream)),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::os::unix::fs::FileTypeExt;
+    use std::path::{Path, PathBuf};
+    use tokio::net::UnixListener;
+    use tracing::warn;
+    
+    pub struct UnixSocket {
+        pub(super) listener: UnixListener,
+        path: PathBuf,
+    }
+    
+    impl UnixSocket {
+        pub fn bind(path: &Path) -> std::io::Result<Self> {
+            if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                if metadata.file_type().is_socket() {
+                    std::fs::remove_file(path)?;
+                }
+            }
+            
+            Ok(UnixSocket {
+                listener: UnixListener::bind(path)?,
+                path: path.to_path_buf(),
+            })
+        }
+    }
+    
+    impl Drop for UnixSocket {
+        fn drop(&mut self) {
+            if let Err(e) = std::fs::remove_file(&self.path) {
+                warn!("⚠️ Не удалось удалить сокет {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+pub struct Connections {
+    done: mpsc::Receiver<()>,
+}
+
+impl Connections {
+    pub async fn drained(mut self) {
+        let _ = self.done.recv().await;
+    }
+}
+
+async fn stopped(stop: &mut watch::Receiver<bool>) {
+    let _ = stop.wait_for(|stopped| *stopped).await;
+}
+
+pub async fn serve(listener: Listener, app: Router, keepalive: Duration, mut stop: watch::Receiver<bool>) -> Connections {
+    let (done_tx, done) = mpsc::channel::<()>(1);
+    
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    error!("❌ Ошибка принятия соединения: {}", e);
+                    continue;
+                }
+            },
+            _ = stopped(&mut stop) => break,
+        };
+        
+        let connection = Connection {
+            app: app.clone(),
+            keepalive,
+            stop: stop.clone(),
+            done: done_tx.clone(),
+        };
+        match accepted {
+            Accepted::Tcp(stream, peer) => connection.spawn(stream, Some(normalize(peer))),
+            #[cfg(unix)]
+            Accepted::Unix(stream) => connection.spawn(stream, None),
+        }
+    }
+    
+    Connections { done }
+}
+
+pub fn normalize(peer: SocketAddr) -> SocketAddr {
+    SocketAddr::new(peer.ip().to_canonical(), peer.port())
+}
+
+struct Connection {
+    app: Router,
+    keepalive: Duration,
+    stop: watch::Receiver<bool>,
+    done: mpsc::Sender<()>,
+}
+
+impl Connection {
+    fn spawn<S>(self, stream: S, peer: Option<SocketAddr>)
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let Connection { app, keepalive, mut stop, done } = self;
+        let service = TowerToHyperService::new(app.map_request(move |mut request: Request<Incoming>| {
+            if let Some(peer) = peer {
+                request.extensions_mut().insert(ConnectInfo(peer));
+            }
+            request
+        }));
+        let peer = peer.map_or_else(|| "unix".to_string(), |peer| peer.to_string());
+        tokio::spawn(async move {
+            let _done = done;
+            let mut builder = http1::Builder::new();
+            builder.timer(TokioTimer::new());
+            if keepalive.is_zero() {
+                builder.keep_alive(false);
+            } else {
+                builder.header_read_timeout(keepalive);
+            }
+            
+            let connection = builder.serve_connection(TokioIo::new(stream), service).with_upgrades();
+            tokio::pin!(connection);
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                _ = stopped(&mut stop) => {
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                debug!("Соединение {} закрыто: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn maps_ipv4_mapped_ipv6_to_ipv4() {
+        let peer: SocketAddr = "[::ffff:10.0.0.1]:4000".parse().unwrap();
+        assert_eq!(normalize(peer), "10.0.0.1:4000".parse::<SocketAddr>().unwrap());
+    }
+    
+    #[test]
+    fn keeps_plain_addresses() {
+        for peer in ["[2001:db8::1]:4000", "[::1]:4000", "10.0.0.1:4000"] {
+            let peer: SocketAddr = peer.parse().unwrap();
+            assert_eq!(normalize(peer), peer);
+        }
+    }
+}
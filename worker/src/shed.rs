@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::json;
+use std::fmt;
+use std::str::FromStr;
+use tracing::debug;
+
+use crate::NodeState;
+
+pub const PROTECTED: [&str; 5] = ["/api/health", "/api/ready", "/api/ping", "/api/drain", "/api/undrain"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tier {
+    pub percent: i32,
+    pub paths: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tiers(pub Vec<Tier>);
+
+impl Tiers {
+    fn threshold(&self, path: &str) -> Option<i32> {
+        self.0
+            .iter()
+            .filter(|tier| tier.paths.iter().any(|tier_path| tier_path == path))
+            .map(|tier| tier.percent)
+            .min()
+    }
+    
+    pub fn protected_paths(&self) -> Vec<&str> {
+        self.0
+            .iter()
+            .flat_map(|tier| tier.paths.iter().map(String::as_str))
+            .filter(|path| PROTECTED.contains(path))
+            .collect()
+    }
+}
+
+impl FromStr for Tiers {
+    type Err = String;
+    
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        value
+            .split(';')
+            .filter(|tier| !tier.trim().is_empty())
+            .map(|tier| {
+                let (percent, paths) = tier
+                    .split_once(':')
+                    .ok_or_else(|| format!("ожидается порог:путь,путь, получено {}", tier.trim()))?;
+                let percent = percent
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|percent| (1..=100).contains(percent))
+                    .ok_or_else(|| format!("порог {} должен быть от 1 до 100", percent.trim()))?;
+                let paths = paths.split(',').map(str::trim).filter(|path| !path.is_empty()).map(String::from).collect();
+                Ok(Tier { percent, paths })
+            })
+            .collect::<Result<_, _>>()
+            .map(Tiers)
+    }
+}
+
+impl fmt::Display for Tiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tiers: Vec<String> = self.0.iter().map(|tier| format!("{}:{}", tier.percent, tier.paths.join(","))).collect();
+        write!(f, "{}", tiers.join(";"))
+    }
+}
+
+pub fn route_path<'a>(route_prefix: &str, path: &'a str) -> &'a str {
+    match path.strip_prefix(route_prefix) {
+        Some("") => "/",
+        Some(stripped) => stripped,
+        None => path,
+    }
+}
+
+pub async fn shed_load(State(state): State<NodeState>, request: Request, next: Next) -> Response {
+    let config = state.config();
+    let path = route_path(&config.route_prefix, request.uri().path());
+    let Some(threshold) = config.load_shed_tiers.threshold(path) else {
+        return next.run(request).await;
+    };
+    
+    let load_percent = *state.load.lock().await * 100 / config.capacity;
+    if load_percent < threshold {
+        return next.run(request).await;
+    }
+    
+    let reason = format!("нагрузка {}% не ниже порога {}% для {}", load_percent, threshold, path);
+    debug!("Запрос отброшен при перегрузке: {}", reason);
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({ "error": "overloaded", "reason": reason })),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(config.load_interval_secs));
+    
+    response
+}
@@ -0,0 +1,60 @@
+use std::future::Future;
+use tokio::time::{timeout, Duration, Instant};
+use tracing::{info, warn};
+
+pub const STEPS: [(&str, u32); 6] = [
+    ("остановка приёма HTTP", 5),
+    ("дренаж HTTP-запросов", 50),
+    ("финальное обновление нагрузки", 15),
+    ("снятие с регистрации", 15),
+    ("остановка фоновых задач", 10),
+    ("закрытие связи с мастером", 5),
+];
+const DRAIN_STEP: usize = 1;
+
+pub fn drain_share(budget: Duration) -> Duration {
+    budget * STEPS[DRAIN_STEP].1 / 100
+}
+
+pub struct Sequence {
+    budget: Duration,
+    started: Instant,
+    step: usize,
+}
+
+impl Sequence {
+    pub fn new(budget: Duration) -> Self {
+        info!("🛑 Остановка ноды, бюджет {} мс", budget.as_millis());
+        Sequence {
+            budget,
+            started: Instant::now(),
+            step: 0,
+        }
+    }
+    
+    pub async fn run<F: Future>(&mut self, future: F) -> Option<F::Output> {
+        self.run_capped(Duration::MAX, future).await
+    }
+    
+    pub async fn run_capped<F: Future>(&mut self, cap: Duration, future: F) -> Option<F::Output> {
+        let (name, share) = STEPS[self.step];
+        self.step += 1;
+        
+        let limit = (self.budget * share / 100).min(cap);
+        let started = Instant::now();
+        match timeout(limit, future).await {
+            Ok(output) => {
+                info!("✅ Шаг {}/{} «{}» завершён за {} мс", self.step, STEPS.len(), name, started.elapsed().as_millis());
+                Some(output)
+            }
+            Err(_) => {
+                warn!("⏱️ Шаг {}/{} «{}» не уложился в {} мс, пропущен", self.step, STEPS.len(), name, limit.as_millis());
+                None
+            }
+        }
+    }
+    
+    pub fn finish(self) {
+        info!("👋 Нода остановлена за {} мс", self.started.elapsed().as_millis());
+    }
+}
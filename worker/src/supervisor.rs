@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Notify;
+use tokio::time::{timeout, Duration};
+use tracing::warn;
+
+const RESTART_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct TaskRestarts {
+    pub simulate_load: u64,
+    pub heartbeat: u64,
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    restart: Notify,
+    restarted: Notify,
+    load_restarts: AtomicU64,
+    heartbeat_restarts: AtomicU64,
+}
+
+impl Supervisor {
+    pub fn restarts(&self) -> TaskRestarts {
+        TaskRestarts {
+            simulate_load: self.load_restarts.load(Ordering::SeqCst),
+            heartbeat: self.heartbeat_restarts.load(Ordering::SeqCst),
+        }
+    }
+    
+    pub async fn restart(&self) -> TaskRestarts {
+        warn!("♻️ Перезапуск фоновых задач по запросу");
+        let restarted = self.restarted.notified();
+        tokio::pin!(restarted);
+        restarted.as_mut().enable();
+        self.restart.notify_one();
+        
+        let _ = timeout(RESTART_WAIT, restarted).await;
+        self.restarts()
+    }
+    
+    pub async fn run<L, H>(&self, load: impl Fn() -> L, heartbeat: impl Fn() -> H)
+    where
+        L: Future<Output = ()>,
+        H: Future<Output = ()>,
+    {
+        let mut load_task = Box::pin(load());
+        let mut heartbeat_task = Box::pin(heartbeat());
+        loop {
+            tokio::select! {
+                _ = &mut load_task => {
+                    let restarts = self.load_restarts.fetch_add(1, Ordering::SeqCst) + 1;
+                    warn!("⚠️ Задача simulate_load завершилась, перезапуск №{}", restarts);
+                    load_task.set(load());
+                }
+                _ = &mut heartbeat_task => {
+                    let restarts = self.heartbeat_restarts.fetch_add(1, Ordering::SeqCst) + 1;
+                    warn!("⚠️ Задача heartbeat завершилась, перезапуск №{}", restarts);
+                    heartbeat_task.set(heartbeat());
+                }
+                _ = self.restart.notified() => {
+                    load_task.set(load());
+                    heartbeat_task.set(heartbeat());
+                    self.load_restarts.fetch_add(1, Ordering::SeqCst);
+                    self.heartbeat_restarts.fetch_add(1, Ordering::SeqCst);
+                    self.restarted.notify_waiters();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::Arc;
+    
+    #[tokio::test]
+    async fn counts_restarts_per_task() {
+        let supervisor = Arc::new(Supervisor::default());
+        let load_runs = Arc::new(AtomicU32::new(0));
+        
+        let runner = supervisor.clone();
+        let runs = load_runs.clone();
+        tokio::spawn(async move {
+            runner
+                .run(
+                    || {
+                        let runs = runs.clone();
+                        async move {
+                            if runs.fetch_add(1, Ordering::SeqCst) < 2 {
+                                return;
+                            }
+                            std::future::pending::<()>().await
+                        }
+                    },
+                    std::future::pending::<()>,
+                )
+                .await
+        });
+        
+        while load_runs.load(Ordering::SeqCst) < 3 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(supervisor.restarts(), TaskRestarts { simulate_load: 2, heartbeat: 0 });
+        
+        assert_eq!(supervisor.restart().await, TaskRestarts { simulate_load: 3, heartbeat: 1 });
+    }
+}
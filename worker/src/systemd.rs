@@ -0,0 +1,59 @@
+use std::env;
+use std::time::Duration;
+use tracing::warn;
+
+pub fn enabled() -> bool {
+    cfg!(all(unix, feature = "systemd")) && env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+pub fn warn_if_unsupported() {
+    if !cfg!(all(unix, feature = "systemd")) && env::var_os("NOTIFY_SOCKET").is_some() {
+        warn!("⚠️ NOTIFY_SOCKET задан, но воркер собран без feature \"systemd\": systemd не получит READY=1");
+    }
+}
+
+pub fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    Some(Duration::from_micros(usec) / 2).filter(|interval| !interval.is_zero())
+}
+
+#[cfg(all(unix, feature = "systemd"))]
+pub fn notify(message: &str) {
+    if !enabled() {
+        return;
+    }
+    
+    if let Err(e) = send(message) {
+        warn!("⚠️ Не удалось отправить systemd {}: {}", message, e);
+    }
+}
+
+#[cfg(not(all(unix, feature = "systemd")))]
+pub fn notify(_message: &str) {}
+
+#[cfg(all(unix, feature = "systemd"))]
+fn send(message: &str) -> std::io::Result<()> {
+    use std::os::unix::net::UnixDatagram;
+    
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+    
+    #[cfg(target_os = "linux")]
+    if let Some(name) = path.as_encoded_bytes().strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+        
+        socket.send_to_addr(message.as_bytes(), &SocketAddr::from_abstract_name(name)?)?;
+        return Ok(());
+    }
+    
+    socket.send_to(message.as_bytes(), path)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::env;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Registry;
+
+pub type LevelHandle = reload::Handle<LevelFilter, Registry>;
+
+pub struct Telemetry {
+    level: LevelHandle,
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Telemetry {
+    pub fn level_handle(&self) -> LevelHandle {
+        self.level.clone()
+    }
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("❌ Ошибка остановки экспорта трейсов: {}", e);
+            }
+        }
+    }
+}
+
+pub fn set_level(handle: &LevelHandle, level: LevelFilter) {
+    match handle.reload(level) {
+        Ok(()) => {}
+        Err(e) if e.is_dropped() => {}
+        Err(e) => tracing::error!("❌ Не удалось сменить уровень логирования: {}", e),
+    }
+}
+
+#[cfg(feature = "otel")]
+fn init_otel(endpoint: &str) -> Result<Telemetry, Box<dyn std::error::Error>> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    
+    let exporter = SpanExporter::builder().with_http().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("worker").build())
+        .build();
+    
+    let (filter, level) = reload::Layer::new(LevelFilter::INFO);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("worker")))
+        .try_init()?;
+    
+    tracing::info!("🔭 Экспорт трейсов OTLP: {}", endpoint);
+    Ok(Telemetry { level, provider: Some(provider) })
+}
+
+pub fn init() -> Telemetry {
+    let endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|value| !value.is_empty());
+    
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = &endpoint {
+        match init_otel(endpoint) {
+            Ok(telemetry) => return telemetry,
+            Err(e) => eprintln!("❌ Не удалось включить OpenTelemetry: {}", e),
+        }
+    }
+    
+    let (filter, level) = reload::Layer::new(LevelFilter::INFO);
+    let _ = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .try_init();
+    
+    #[cfg(not(feature = "otel"))]
+    if endpoint.is_some() {
+        tracing::warn!("⚠️ OTEL_EXPORTER_OTLP_ENDPOINT задан, но воркер собран без feature \"otel\"");
+    }
+    
+    Telemetry {
+        level,
+        #[cfg(feature = "otel")]
+        provider: None,
+    }
+}